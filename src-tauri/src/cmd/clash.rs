@@ -1,8 +1,17 @@
 use super::CmdResult;
 use crate::{
-    config::*, core::*, feat, module::mihomo::MihomoManager, process::AsyncHandler, wrap_err,
+    config::*,
+    core::*,
+    feat,
+    module::{
+        latency::{LatencyHistory, LatencyStats},
+        mihomo::MihomoManager,
+    },
+    process::AsyncHandler,
+    wrap_err,
 };
 use serde_yaml::Mapping;
+use std::time::Duration;
 
 /// 复制Clash环境变量
 #[tauri::command]
@@ -89,10 +98,73 @@ pub async fn clash_api_get_proxy_delay(
     name: String,
     url: Option<String>,
     timeout: i32,
-) -> CmdResult<serde_json::Value> {
-    MihomoManager::global()
+) -> CmdResult<mihomo_api::ProxyDelay> {
+    let delay = MihomoManager::global()
         .test_proxy_delay(&name, url, timeout)
-        .await
+        .await?;
+    Ok(delay)
+}
+
+/// 切换代理组节点，并记录到当前订阅的 `selected`
+#[tauri::command]
+pub async fn clash_api_select_proxy(group: String, name: String) -> CmdResult {
+    wrap_err!(feat::select_proxy(group, name).await)
+}
+
+/// 转发前端的控制器请求，用于只开放 unix socket、前端无法直连内核的场景
+#[tauri::command]
+pub async fn clash_api_request(
+    method: String,
+    path: String,
+    data: Option<serde_json::Value>,
+    timeout: Option<u64>,
+) -> CmdResult<serde_json::Value> {
+    let method =
+        reqwest::Method::from_bytes(method.to_uppercase().as_bytes()).map_err(|e| e.to_string())?;
+    let timeout = Duration::from_millis(timeout.unwrap_or(15000));
+    let body = MihomoManager::global()
+        .request_raw(method, &path, data, timeout)
+        .await?;
+    Ok(body)
+}
+
+/// 订阅控制器推送，返回订阅 id，消息通过 `verge://clash-stream` 事件下发
+#[tauri::command]
+pub fn clash_api_subscribe(path: String) -> CmdResult<u32> {
+    wrap_err!(feat::subscribe_clash_stream(path))
+}
+
+/// 取消控制器推送订阅
+#[tauri::command]
+pub fn clash_api_unsubscribe(id: u32) -> CmdResult {
+    feat::unsubscribe_clash_stream(id);
+    Ok(())
+}
+
+/// 批量测试节点延迟，`group` 为空时测试全部节点
+#[tauri::command]
+pub async fn clash_api_batch_test_delay(
+    group: Option<String>,
+    concurrency: Option<usize>,
+    timeout: Option<i32>,
+    url: Option<String>,
+) -> CmdResult<Vec<feat::DelayTestResult>> {
+    wrap_err!(feat::batch_test_delay(group, concurrency, timeout, url).await)
+}
+
+/// 获取节点延迟统计，`sort_by` 可选 `median` / `jitter` / `loss`
+#[tauri::command]
+pub fn get_latency_stats(sort_by: Option<String>) -> CmdResult<Vec<LatencyStats>> {
+    let sort_by = sort_by.unwrap_or("median".into());
+    Ok(LatencyHistory::global().lock().sorted_stats(&sort_by))
+}
+
+/// 清空延迟历史
+#[tauri::command]
+pub fn clear_latency_history() -> CmdResult {
+    let mut history = LatencyHistory::global().lock();
+    history.clear();
+    wrap_err!(history.save())
 }
 
 /// 测试URL延迟
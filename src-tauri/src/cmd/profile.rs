@@ -1,19 +1,24 @@
 use super::CmdResult;
 use crate::{
-    config::{Config, IProfiles, PrfItem, PrfOption},
+    config::{Config, GlobalChainItem, IProfiles, PrfItem, PrfOption},
     core::{handle, timer::Timer, tray::Tray, CoreManager},
     feat, logging, ret_err,
-    utils::{dirs, help, logging::Type},
+    utils::{
+        convert::{
+            rules::{convert_rule_list, RuleListImport},
+            share_link::{export_share_link, parse_share_link, SharedLink},
+            uri_list::{export_uri_list, UriListExport},
+        },
+        dirs, help,
+        logging::Type,
+    },
     wrap_err,
 };
-use base64::{engine::general_purpose::STANDARD, Engine as _};
-use percent_encoding::percent_decode_str;
-use serde_yaml::Value;
-use std::collections::BTreeMap;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use tokio::sync::{Mutex, RwLock};
-use url::Url;
 
 // 全局互斥锁防止并发配置更新
 static PROFILE_UPDATE_MUTEX: Mutex<()> = Mutex::const_new(());
@@ -48,6 +53,7 @@ pub async fn get_profiles() -> CmdResult<IProfiles> {
             IProfiles {
                 current: latest.current.clone(),
                 items: latest.items.clone(),
+                global_chain: latest.global_chain.clone(),
             }
         }),
     )
@@ -91,6 +97,7 @@ pub async fn get_profiles() -> CmdResult<IProfiles> {
             IProfiles {
                 current: data.current.clone(),
                 items: data.items.clone(),
+                global_chain: data.global_chain.clone(),
             }
         }),
     )
@@ -155,6 +162,7 @@ pub async fn get_profiles() -> CmdResult<IProfiles> {
             Ok(IProfiles {
                 current: None,
                 items: Some(vec![]),
+                global_chain: None,
             })
         }
     }
@@ -200,13 +208,14 @@ pub async fn import_profile(url: String, option: Option<PrfOption>) -> CmdResult
             "Profile with URL {} not found. Create a new one...",
             url
         );
-        let item = wrap_err!(PrfItem::from_url(&url, None, None, option).await)?;
+        let item = wrap_err!(PrfItem::from_url(&url, None, None, option, None).await)?;
         let new_uid = item.uid.clone().unwrap_or_default();
         wrap_err!(Config::profiles().data().append_item(item))?;
         if !new_uid.is_empty() {
             let _ = patch_profiles_config(IProfiles {
                 current: Some(new_uid),
                 items: None,
+                global_chain: None,
             })
             .await?;
         }
@@ -214,6 +223,113 @@ pub async fn import_profile(url: String, option: Option<PrfOption>) -> CmdResult
     }
 }
 
+/// 获取全局扩展列表
+#[tauri::command]
+pub fn get_global_chain() -> CmdResult<Vec<GlobalChainItem>> {
+    Ok(Config::profiles().latest().get_global_chain())
+}
+
+/// 修改全局扩展的顺序和启用状态，并重新生成配置
+#[tauri::command]
+pub async fn patch_global_chain(chain: Vec<GlobalChainItem>) -> CmdResult {
+    wrap_err!(Config::profiles().data().set_global_chain(chain))?;
+    wrap_err!(feat::enhance_profiles().await)?;
+    handle::Handle::refresh_clash();
+    Ok(())
+}
+
+/// 修改订阅引用的扩展列表，当前订阅会重新生成配置
+#[tauri::command]
+pub async fn patch_profile_chain(index: String, chain: Vec<String>) -> CmdResult {
+    let is_current = {
+        let profiles = Config::profiles();
+        let mut profiles = profiles.data();
+        wrap_err!(profiles.set_profile_chain(index.clone(), chain))?;
+        profiles.is_current_profile_index(index)
+    };
+    if is_current {
+        wrap_err!(feat::enhance_profiles().await)?;
+        handle::Handle::refresh_clash();
+    }
+    Ok(())
+}
+
+/// 获取使用该扩展的订阅
+#[tauri::command]
+pub fn get_chain_users(index: String) -> CmdResult<Vec<String>> {
+    Ok(Config::profiles().latest().chain_users(&index))
+}
+
+/// 将 Surge / Quantumult X / Loon 规则列表转换后导入订阅的规则扩展
+///
+/// 规则来自粘贴的文本或文件，append 为 false 时插入到 prepend
+#[tauri::command]
+pub async fn import_rule_list(
+    index: String,
+    text: Option<String>,
+    path: Option<String>,
+    policy: Option<String>,
+    append: bool,
+) -> CmdResult<RuleListImport> {
+    let text = match (text, path) {
+        (Some(text), _) => text,
+        (None, Some(path)) => wrap_err!(std::fs::read_to_string(path))?,
+        (None, None) => ret_err!("no rule list provided"),
+    };
+    let result = convert_rule_list(&text, policy.as_deref());
+
+    let (file, is_current) = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let item = wrap_err!(profiles.get_item(&index))?;
+        let rules_uid = item
+            .option
+            .as_ref()
+            .and_then(|option| option.rules.clone())
+            .ok_or("the profile has no rules item")?;
+        let rules = wrap_err!(profiles.get_item(&rules_uid))?;
+        let file = rules.file.clone().ok_or("the file field is null")?;
+        (file, profiles.is_current_profile_index(index))
+    };
+
+    let path = wrap_err!(dirs::app_profiles_dir())?.join(file);
+    let mut seq_map = wrap_err!(help::read_seq_map(&path))?;
+    let existing = seq_map
+        .prepend
+        .iter()
+        .chain(seq_map.append.iter())
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect::<HashSet<_>>();
+    let rules = result
+        .rules
+        .iter()
+        .filter(|rule| !existing.contains(*rule))
+        .map(|rule| Value::String(rule.clone()))
+        .collect::<Vec<_>>();
+    if append {
+        seq_map.append.extend(rules);
+    } else {
+        seq_map.prepend.splice(0..0, rules);
+    }
+
+    // 保留文件开头的注释
+    let comments = std::fs::read_to_string(&path)
+        .unwrap_or_default()
+        .lines()
+        .take_while(|line| line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prefix = (!comments.is_empty()).then_some(comments.as_str());
+    wrap_err!(help::save_yaml(&path, &seq_map, prefix))?;
+
+    if is_current {
+        wrap_err!(feat::enhance_profiles().await)?;
+        handle::Handle::refresh_clash();
+    }
+    Ok(result)
+}
+
 /// 重新排序配置文件
 #[tauri::command]
 pub async fn reorder_profile(active_id: String, over_id: String) -> CmdResult {
@@ -231,6 +347,7 @@ pub async fn create_profile(item: PrfItem, file_data: Option<String>) -> CmdResu
         let _ = patch_profiles_config(IProfiles {
             current: Some(new_uid),
             items: None,
+            global_chain: None,
         })
         .await?;
     }
@@ -255,11 +372,10 @@ pub async fn delete_profile(index: String) -> CmdResult {
             .delete_item(index.clone())
             .map_err(|e| e.to_string())?;
 
-        let was_last_profile = profiles_data.items.as_ref().is_none_or(|items| {
-            !items
-                .iter()
-                .any(|item| matches!(item.itype.as_deref(), Some("remote") | Some("local")))
-        });
+        let was_last_profile = profiles_data
+            .items
+            .as_ref()
+            .is_none_or(|items| !items.iter().any(|item| item.is_profile()));
 
         if was_last_profile {
             logging!(
@@ -644,6 +760,7 @@ pub async fn patch_profiles_config(profiles: IProfiles) -> CmdResult<bool> {
                 let restore_profiles = IProfiles {
                     current: Some(prev_profile),
                     items: None,
+                    global_chain: None,
                 };
                 // 静默恢复，不触发验证
                 wrap_err!({ Config::profiles().draft().patch_config(restore_profiles) })?;
@@ -711,6 +828,7 @@ pub async fn patch_profiles_config(profiles: IProfiles) -> CmdResult<bool> {
                 let restore_profiles = IProfiles {
                     current: Some(prev_profile),
                     items: None,
+                    global_chain: None,
                 };
                 wrap_err!({ Config::profiles().draft().patch_config(restore_profiles) })?;
                 Config::profiles().apply();
@@ -742,6 +860,7 @@ pub async fn patch_profiles_config_by_profile_index(
     let profiles = IProfiles {
         current: Some(profile_index),
         items: None,
+        global_chain: None,
     };
     patch_profiles_config(profiles).await
 }
@@ -758,10 +877,39 @@ pub fn patch_profile(index: String, profile: PrfItem) -> CmdResult {
         } else {
             false
         };
+    // 聚合订阅的来源变更后需要重新生成
+    let sources_changed = profile
+        .option
+        .as_ref()
+        .and_then(|o| o.sources.as_ref())
+        .is_some_and(|sources| {
+            Config::profiles()
+                .latest()
+                .get_item(&index)
+                .map(|old| old.option.as_ref().and_then(|o| o.sources.as_ref()) != Some(sources))
+                .unwrap_or(false)
+        });
 
     // 保存修改
     wrap_err!(Config::profiles().data().patch_item(index.clone(), profile))?;
 
+    if sources_changed {
+        let is_current = {
+            let profiles = Config::profiles();
+            let mut profiles = profiles.data();
+            wrap_err!(profiles.regenerate_aggregate(&index))?;
+            profiles.get_current().as_ref() == Some(&index)
+        };
+        if is_current {
+            crate::process::AsyncHandler::spawn(|| async move {
+                match feat::enhance_profiles().await {
+                    Ok(_) => handle::Handle::refresh_clash(),
+                    Err(err) => logging!(error, Type::Cmd, true, "{}", err),
+                }
+            });
+        }
+    }
+
     // 如果更新间隔变更，异步刷新定时器
     if update_interval_changed {
         let index_clone = index.clone();
@@ -1252,106 +1400,18 @@ pub async fn create_profile_from_share_link(link: String, template_name: String)
         _ => DEFAULT_TEMPLATE,
     };
 
-    let parsed_url = Url::parse(&link).map_err(|e| e.to_string())?;
-    let scheme = parsed_url.scheme();
-    let proxy_name = parsed_url
-        .fragment()
-        .map(|f| percent_decode_str(f).decode_utf8_lossy().to_string())
-        .unwrap_or_else(|| "Proxy from Link".to_string());
-
-    let mut proxy_map: BTreeMap<String, Value> = BTreeMap::new();
-    proxy_map.insert("name".into(), proxy_name.clone().into());
-    proxy_map.insert("type".into(), scheme.into());
-    proxy_map.insert(
-        "server".into(),
-        parsed_url.host_str().unwrap_or_default().into(),
-    );
-    proxy_map.insert("port".into(), parsed_url.port().unwrap_or(443).into());
-    proxy_map.insert("udp".into(), true.into());
-
-    match scheme {
-        "vless" | "trojan" => {
-            proxy_map.insert("uuid".into(), parsed_url.username().into());
-            let mut reality_opts: BTreeMap<String, Value> = BTreeMap::new();
-            for (key, value) in parsed_url.query_pairs() {
-                match key.as_ref() {
-                    "security" if value == "reality" => {
-                        proxy_map.insert("tls".into(), true.into());
-                    }
-                    "security" if value == "tls" => {
-                        proxy_map.insert("tls".into(), true.into());
-                    }
-                    "flow" => {
-                        proxy_map.insert("flow".into(), value.to_string().into());
-                    }
-                    "sni" => {
-                        proxy_map.insert("servername".into(), value.to_string().into());
-                    }
-                    "fp" => {
-                        proxy_map.insert("client-fingerprint".into(), value.to_string().into());
-                    }
-                    "pbk" => {
-                        reality_opts.insert("public-key".into(), value.to_string().into());
-                    }
-                    "sid" => {
-                        reality_opts.insert("short-id".into(), value.to_string().into());
-                    }
-                    _ => {}
-                }
-            }
-            if !reality_opts.is_empty() {
-                proxy_map.insert(
-                    "reality-opts".into(),
-                    serde_yaml::to_value(reality_opts).map_err(|e| e.to_string())?,
-                );
-            }
-        }
-        "ss" => {
-            if let Ok(decoded_user) = STANDARD.decode(parsed_url.username()) {
-                if let Ok(user_str) = String::from_utf8(decoded_user) {
-                    if let Some((cipher, password)) = user_str.split_once(':') {
-                        proxy_map.insert("cipher".into(), cipher.into());
-                        proxy_map.insert("password".into(), password.into());
-                    }
-                }
-            }
-        }
-        "vmess" => {
-            if let Ok(decoded_bytes) = STANDARD.decode(parsed_url.host_str().unwrap_or_default()) {
-                if let Ok(json_str) = String::from_utf8(decoded_bytes) {
-                    if let Ok(vmess_params) =
-                        serde_json::from_str::<BTreeMap<String, Value>>(&json_str)
-                    {
-                        if let Some(add) = vmess_params.get("add") {
-                            proxy_map.insert("server".into(), add.clone());
-                        }
-                        if let Some(port) = vmess_params.get("port") {
-                            proxy_map.insert("port".into(), port.clone());
-                        }
-                        if let Some(id) = vmess_params.get("id") {
-                            proxy_map.insert("uuid".into(), id.clone());
-                        }
-                        if let Some(aid) = vmess_params.get("aid") {
-                            proxy_map.insert("alterId".into(), aid.clone());
-                        }
-                        if let Some(net) = vmess_params.get("net") {
-                            proxy_map.insert("network".into(), net.clone());
-                        }
-                        if let Some(ps) = vmess_params.get("ps") {
-                            proxy_map.insert("name".into(), ps.clone());
-                        }
-                    }
-                }
-            }
-        }
-        _ => {}
-    }
+    let proxy = parse_share_link(&link)?;
+    let proxy_name = proxy
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or("Proxy from Link")
+        .to_string();
 
     let mut config: Value = serde_yaml::from_str(template_yaml).map_err(|e| e.to_string())?;
 
     if let Some(proxies) = config.get_mut("proxies").and_then(|v| v.as_sequence_mut()) {
         proxies.clear();
-        proxies.push(serde_yaml::to_value(proxy_map).map_err(|e| e.to_string())?);
+        proxies.push(Value::Mapping(proxy));
     }
 
     if let Some(groups) = config
@@ -1391,3 +1451,87 @@ pub async fn create_profile_from_share_link(link: String, template_name: String)
 
     wrap_err!(Config::profiles().data().append_item(item))
 }
+
+/// 读取订阅中的代理，index 为空时读取运行时配置
+fn read_proxies(index: Option<&String>) -> CmdResult<Vec<Value>> {
+    let config = match index {
+        Some(index) => {
+            let profiles = Config::profiles();
+            let profiles = profiles.latest();
+            let item = wrap_err!(profiles.get_item(index))?;
+            let data = wrap_err!(item.read_file())?;
+            wrap_err!(serde_yaml::from_str::<Mapping>(&data))?
+        }
+        None => Config::runtime()
+            .latest()
+            .config
+            .clone()
+            .ok_or("runtime config is not generated".to_string())?,
+    };
+    Ok(config
+        .get("proxies")
+        .and_then(Value::as_sequence)
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// 将代理导出为分享链接，names 为空时导出全部代理
+#[tauri::command]
+pub fn export_share_links(
+    index: Option<String>,
+    names: Option<Vec<String>>,
+) -> CmdResult<Vec<SharedLink>> {
+    let proxies = read_proxies(index.as_ref())?;
+    Ok(proxies
+        .iter()
+        .filter_map(Value::as_mapping)
+        .filter(|proxy| {
+            let name = proxy
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            names
+                .as_ref()
+                .is_none_or(|names| names.iter().any(|n| n == name))
+        })
+        .map(export_share_link)
+        .collect())
+}
+
+/// 将订阅导出为 base64 编码的分享链接订阅，指定 path 时写入文件
+#[tauri::command]
+pub fn export_profile_subscription(
+    index: String,
+    path: Option<String>,
+) -> CmdResult<UriListExport> {
+    let proxies = read_proxies(Some(&index))?;
+    let result = export_uri_list(&proxies);
+    if result.links.iter().all(|link| link.link.is_none()) {
+        ret_err!("the profile does not contain any exportable proxy");
+    }
+    for link in &result.links {
+        if let Some(error) = &link.error {
+            logging!(
+                warn,
+                Type::Cmd,
+                true,
+                "[Export] skip {}: {}",
+                link.name,
+                error
+            );
+        } else if !link.unsupported.is_empty() {
+            logging!(
+                warn,
+                Type::Cmd,
+                true,
+                "[Export] {} drops fields: {}",
+                link.name,
+                link.unsupported.join(", ")
+            );
+        }
+    }
+    if let Some(path) = path {
+        wrap_err!(std::fs::write(path, &result.content))?;
+    }
+    Ok(result)
+}
@@ -1,5 +1,6 @@
 use super::CmdResult;
 use crate::{core::handle, module::mihomo::MihomoManager, state::proxy::CmdProxyState};
+use mihomo_api::{Proxies, ProxyProviders};
 use std::{
     sync::Mutex,
     time::{Duration, Instant},
@@ -10,7 +11,7 @@ const PROVIDERS_REFRESH_INTERVAL: Duration = Duration::from_secs(3);
 const PROXIES_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
 
 #[tauri::command]
-pub async fn get_proxies() -> CmdResult<serde_json::Value> {
+pub async fn get_proxies() -> CmdResult<Proxies> {
     let manager = MihomoManager::global();
 
     let app_handle = handle::Handle::global().app_handle().unwrap();
@@ -45,7 +46,7 @@ pub async fn get_proxies() -> CmdResult<serde_json::Value> {
 
 /// 强制刷新代理缓存用于profile切换
 #[tauri::command]
-pub async fn force_refresh_proxies() -> CmdResult<serde_json::Value> {
+pub async fn force_refresh_proxies() -> CmdResult<Proxies> {
     let manager = MihomoManager::global();
     let app_handle = handle::Handle::global().app_handle().unwrap();
     let cmd_proxy_state = app_handle.state::<Mutex<CmdProxyState>>();
@@ -66,7 +67,7 @@ pub async fn force_refresh_proxies() -> CmdResult<serde_json::Value> {
 }
 
 #[tauri::command]
-pub async fn get_providers_proxies() -> CmdResult<serde_json::Value> {
+pub async fn get_providers_proxies() -> CmdResult<ProxyProviders> {
     let app_handle = handle::Handle::global().app_handle().unwrap();
     let cmd_proxy_state = app_handle.state::<Mutex<CmdProxyState>>();
 
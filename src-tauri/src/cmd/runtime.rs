@@ -1,5 +1,16 @@
 use super::CmdResult;
-use crate::{config::*, wrap_err};
+use crate::{
+    config::*,
+    enhance::{
+        self,
+        lint::LintIssue,
+        pass::PassInfo,
+        route::{match_rules, RouteQuery, RouteResult},
+        trace::EnhanceStep,
+    },
+    utils::dirs,
+    wrap_err,
+};
 use anyhow::Context;
 use serde_yaml::Mapping;
 use std::collections::HashMap;
@@ -34,3 +45,33 @@ pub fn get_runtime_exists() -> CmdResult<Vec<String>> {
 pub fn get_runtime_logs() -> CmdResult<HashMap<String, Vec<(String, String)>>> {
     Ok(Config::runtime().latest().chain_logs.clone())
 }
+
+/// 获取增强流程每一步的配置变化
+#[tauri::command]
+pub fn get_runtime_trace() -> CmdResult<Vec<EnhanceStep>> {
+    Ok(Config::runtime().latest().chain_trace.clone())
+}
+
+/// 获取运行时配置的检查结果
+#[tauri::command]
+pub fn get_runtime_lint() -> CmdResult<Vec<LintIssue>> {
+    Ok(Config::runtime().latest().lint.clone())
+}
+
+/// 离线判断连接会命中运行时配置中的哪条规则
+#[tauri::command]
+pub fn match_runtime_rule(query: RouteQuery) -> CmdResult<RouteResult> {
+    let config = Config::runtime()
+        .latest()
+        .config
+        .clone()
+        .ok_or("runtime config is not generated".to_string())?;
+    let home = wrap_err!(dirs::app_home_dir())?;
+    Ok(match_rules(&config, &query, &home))
+}
+
+/// 获取当前设置下的增强流程
+#[tauri::command]
+pub fn get_enhance_pipeline() -> CmdResult<Vec<PassInfo>> {
+    Ok(enhance::describe())
+}
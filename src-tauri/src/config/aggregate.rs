@@ -0,0 +1,167 @@
+use crate::utils::convert::unique_name;
+use anyhow::{bail, Result};
+use serde_yaml::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
+
+/// 聚合订阅中选择节点的总代理组，MATCH 规则指向它
+pub const AGGREGATE_PROXY_GROUP: &str = "PROXY";
+
+/// 包含所有节点的 url-test 组
+pub const AGGREGATE_AUTO_GROUP: &str = "Auto";
+
+const AGGREGATE_TEST_URL: &str = "https://www.gstatic.com/generate_204";
+
+/// 聚合订阅的一个来源，name 用作该来源的代理组名称
+#[derive(Debug, Clone)]
+pub struct AggregateSource {
+    pub name: String,
+    pub prefix: Option<String>,
+    pub config: Mapping,
+}
+
+/// 合并各来源的 proxies 生成聚合订阅的配置
+///
+/// 节点名称加上来源的前缀，除名称外完全相同的节点只保留第一个；
+/// 每个来源生成一个 select 组，另有包含所有节点的 url-test 组
+pub fn aggregate_config(sources: &[AggregateSource]) -> Result<Mapping> {
+    let mut names: HashSet<String> = [AGGREGATE_PROXY_GROUP, AGGREGATE_AUTO_GROUP]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    let group_names = sources
+        .iter()
+        .map(|source| unique_name(&mut names, source.name.clone()))
+        .collect::<Vec<_>>();
+
+    // 去掉名称后的节点 -> 保留的节点名称
+    let mut seen: HashMap<Mapping, String> = HashMap::new();
+    let mut proxies = vec![];
+    let mut groups = vec![];
+    for (source, group_name) in sources.iter().zip(group_names) {
+        let mut members = vec![];
+        let source_proxies = source
+            .config
+            .get("proxies")
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_mapping);
+        for proxy in source_proxies {
+            let mut key = proxy.clone();
+            key.shift_remove("name");
+            if let Some(name) = seen.get(&key) {
+                if !members.contains(name) {
+                    members.push(name.clone());
+                }
+                continue;
+            }
+
+            let name = proxy
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let prefix = source.prefix.as_deref().unwrap_or_default();
+            let name = unique_name(&mut names, format!("{prefix}{name}"));
+            let mut proxy = proxy.clone();
+            proxy.insert("name".into(), name.as_str().into());
+            proxies.push(Value::Mapping(proxy));
+            seen.insert(key, name.clone());
+            members.push(name);
+        }
+        // 没有节点的来源不生成代理组，空的代理组无法被内核加载
+        if !members.is_empty() {
+            groups.push(proxy_group(&group_name, "select", members));
+        }
+    }
+    if proxies.is_empty() {
+        bail!("the aggregated profiles do not contain any proxy");
+    }
+
+    let all = proxies
+        .iter()
+        .filter_map(|proxy| proxy.get("name")?.as_str().map(String::from))
+        .collect();
+    let mut auto = proxy_group(AGGREGATE_AUTO_GROUP, "url-test", all);
+    auto.insert("url".into(), AGGREGATE_TEST_URL.into());
+    auto.insert("interval".into(), 300.into());
+
+    let mut selector = vec![AGGREGATE_AUTO_GROUP.to_string()];
+    selector.extend(
+        groups
+            .iter()
+            .filter_map(|group| group.get("name")?.as_str().map(String::from)),
+    );
+    let mut proxy_groups = vec![proxy_group(AGGREGATE_PROXY_GROUP, "select", selector), auto];
+    proxy_groups.extend(groups);
+
+    let mut config = Mapping::new();
+    config.insert("proxies".into(), Value::Sequence(proxies));
+    config.insert(
+        "proxy-groups".into(),
+        Value::Sequence(proxy_groups.into_iter().map(Value::Mapping).collect()),
+    );
+    config.insert(
+        "rules".into(),
+        Value::Sequence(vec![format!("MATCH,{AGGREGATE_PROXY_GROUP}").into()]),
+    );
+    Ok(config)
+}
+
+fn proxy_group(name: &str, kind: &str, proxies: Vec<String>) -> Mapping {
+    let mut group = Mapping::new();
+    group.insert("name".into(), name.into());
+    group.insert("type".into(), kind.into());
+    group.insert(
+        "proxies".into(),
+        Value::Sequence(proxies.into_iter().map(Value::from).collect()),
+    );
+    group
+}
+
+#[test]
+fn test_aggregate_config() {
+    let source = |name: &str, prefix: Option<&str>, proxies: &str| AggregateSource {
+        name: name.into(),
+        prefix: prefix.map(String::from),
+        config: serde_yaml::from_str(&format!("proxies: {proxies}")).unwrap(),
+    };
+    let sources = [
+        source(
+            "Provider A",
+            Some("A | "),
+            r#"[{ name: HK, type: ss, server: 1.1.1.1, port: 443, cipher: aes-128-gcm, password: a },
+                { name: JP, type: ss, server: 2.2.2.2, port: 443, cipher: aes-128-gcm, password: a }]"#,
+        ),
+        source(
+            "Provider B",
+            None,
+            r#"[{ name: Hong Kong, type: ss, server: 1.1.1.1, port: 443, cipher: aes-128-gcm, password: a },
+                { name: Auto, type: trojan, server: 3.3.3.3, port: 443, password: b }]"#,
+        ),
+        source("Empty", None, "[]"),
+    ];
+
+    let config = aggregate_config(&sources).unwrap();
+    let expected = serde_yaml::from_str::<Mapping>(
+        r#"
+        proxies:
+          - { name: "A | HK", type: ss, server: 1.1.1.1, port: 443, cipher: aes-128-gcm, password: a }
+          - { name: "A | JP", type: ss, server: 2.2.2.2, port: 443, cipher: aes-128-gcm, password: a }
+          - { name: Auto 2, type: trojan, server: 3.3.3.3, port: 443, password: b }
+        proxy-groups:
+          - { name: PROXY, type: select, proxies: [Auto, Provider A, Provider B] }
+          - name: Auto
+            type: url-test
+            proxies: ["A | HK", "A | JP", Auto 2]
+            url: https://www.gstatic.com/generate_204
+            interval: 300
+          - { name: Provider A, type: select, proxies: ["A | HK", "A | JP"] }
+          - { name: Provider B, type: select, proxies: ["A | HK", Auto 2] }
+        rules: ["MATCH,PROXY"]
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config, expected);
+
+    assert!(aggregate_config(&[source("Empty", None, "[]")]).is_err());
+}
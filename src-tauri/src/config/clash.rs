@@ -88,6 +88,20 @@ impl IClashTemp {
         let socks_port = Self::guard_socks_port(&config);
         let port = Self::guard_port(&config);
         let ctrl = Self::guard_server_ctrl(&config);
+        // 已配置 unix socket 时，允许把 external-controller 置空以关闭 TCP 控制器，
+        // 此时前端的请求和订阅都经由后端转发
+        #[cfg(unix)]
+        let ctrl = match Self::guard_server_unix(&config) {
+            Some(unix_ctrl) => {
+                config.insert("external-controller-unix".into(), unix_ctrl.into());
+                if Self::is_tcp_ctrl_disabled(&config) {
+                    String::new()
+                } else {
+                    ctrl
+                }
+            }
+            None => ctrl,
+        };
         #[cfg(not(target_os = "windows"))]
         config.insert("redir-port".into(), redir_port.into());
         #[cfg(target_os = "linux")]
@@ -155,6 +169,10 @@ impl IClashTemp {
             socks_port: Self::guard_socks_port(config),
             port: Self::guard_port(config),
             server: Self::guard_client_ctrl(config),
+            #[cfg(unix)]
+            unix_socket: Self::guard_client_unix(config),
+            #[cfg(not(unix))]
+            unix_socket: None,
             secret: config.get("secret").and_then(|value| match value {
                 Value::String(val_str) => Some(val_str.clone()),
                 Value::Bool(val_bool) => Some(val_bool.to_string()),
@@ -264,7 +282,44 @@ impl IClashTemp {
             .unwrap_or("127.0.0.1:9097".into())
     }
 
+    /// `external-controller-unix`，未配置或为空时返回 `None`
+    #[cfg(unix)]
+    pub fn guard_server_unix(config: &Mapping) -> Option<String> {
+        config
+            .get("external-controller-unix")
+            .and_then(|value| value.as_str())
+            .map(|val_str| val_str.trim())
+            .filter(|val_str| !val_str.is_empty())
+            .map(|val_str| val_str.to_owned())
+    }
+
+    /// 客户端使用的 socket 路径，相对路径与内核一样按 app home 目录解析
+    #[cfg(unix)]
+    pub fn guard_client_unix(config: &Mapping) -> Option<String> {
+        let value = Self::guard_server_unix(config)?;
+        let path = std::path::Path::new(&value);
+        if path.is_absolute() {
+            return Some(value);
+        }
+        dirs::app_home_dir()
+            .ok()
+            .and_then(|dir| dir.join(path).to_str().map(|s| s.to_owned()))
+    }
+
+    #[cfg(unix)]
+    fn is_tcp_ctrl_disabled(config: &Mapping) -> bool {
+        config
+            .get("external-controller")
+            .and_then(|value| value.as_str())
+            .is_some_and(|val_str| val_str.trim().is_empty())
+    }
+
+    /// 前端使用的控制器地址，TCP 控制器被关闭时返回空串
     pub fn guard_client_ctrl(config: &Mapping) -> String {
+        #[cfg(unix)]
+        if Self::guard_server_unix(config).is_some() && Self::is_tcp_ctrl_disabled(config) {
+            return String::new();
+        }
         let value = Self::guard_server_ctrl(config);
         match SocketAddr::from_str(value.as_str()) {
             Ok(mut socket) => {
@@ -284,8 +339,11 @@ pub struct ClashInfo {
     pub mixed_port: u16,
     pub socks_port: u16,
     pub port: u16,
-    /// same as `external-controller`
+    /// same as `external-controller`, empty when the TCP controller is disabled
     pub server: String,
+    /// same as `external-controller-unix`, preferred over `server` by the backend when set,
+    /// the webview goes through backend commands when `server` is empty
+    pub unix_socket: Option<String>,
     /// clash secret
     pub secret: Option<String>,
 }
@@ -306,6 +364,7 @@ fn test_clash_info() {
             socks_port: 7898,
             port: 7899,
             server: server.into(),
+            unix_socket: None,
             secret: None,
         }
     }
@@ -355,6 +414,40 @@ fn test_clash_info() {
     );
 }
 
+#[cfg(unix)]
+#[test]
+fn test_clash_info_unix_socket() {
+    let mut map = Mapping::new();
+    map.insert("external-controller".into(), "".into());
+    map.insert(
+        "external-controller-unix".into(),
+        " /run/outclash/mihomo.sock ".into(),
+    );
+    let guarded = IClashTemp::guard(map);
+
+    // 配置了 socket 时保留置空的 external-controller，不写入 TCP 控制器
+    assert_eq!(
+        guarded.get("external-controller").and_then(|v| v.as_str()),
+        Some("")
+    );
+    let info = IClashTemp(guarded).get_client_info();
+    assert_eq!(
+        info.unix_socket.as_deref(),
+        Some("/run/outclash/mihomo.sock")
+    );
+    assert_eq!(info.server, "");
+
+    // 未配置 socket 时空的 external-controller 仍回退到默认端口
+    let mut map = Mapping::new();
+    map.insert("external-controller".into(), "".into());
+    let guarded = IClashTemp::guard(map);
+    assert_eq!(
+        guarded.get("external-controller").and_then(|v| v.as_str()),
+        Some("127.0.0.1:9097")
+    );
+    assert_eq!(IClashTemp(guarded).get_client_info().unix_socket, None);
+}
+
 // These structs model the Clash YAML config for (de)serialization.
 // They are not currently instantiated directly; most reads/writes use Mapping.
 // Keep them for typed boundaries and future migration to strongly-typed config.
@@ -1,13 +1,19 @@
 use super::{Draft, IClashTemp, IProfiles, IRuntime, IVerge};
 use crate::{
-    config::PrfItem,
+    config::{GlobalChainItem, PrfItem},
     core::{handle, CoreManager},
-    enhance, logging,
+    enhance::{
+        self,
+        lint::{lint_config, LintLevel},
+        pass::EnhanceInput,
+    },
+    logging,
     process::AsyncHandler,
     utils::{dirs, help, logging::Type},
 };
 use anyhow::{anyhow, Result};
 use once_cell::sync::OnceCell;
+use serde_yaml::Mapping;
 use std::path::PathBuf;
 use tokio::time::{sleep, Duration};
 
@@ -51,21 +57,28 @@ impl Config {
 
     /// 初始化订阅
     pub async fn init_config() -> Result<()> {
-        if Self::profiles()
-            .data()
-            .get_item(&"Merge".to_string())
-            .is_err()
-        {
-            let merge_item = PrfItem::from_merge(Some("Merge".to_string()))?;
-            Self::profiles().data().append_item(merge_item.clone())?;
-        }
-        if Self::profiles()
-            .data()
-            .get_item(&"Script".to_string())
-            .is_err()
-        {
-            let script_item = PrfItem::from_script(Some("Script".to_string()))?;
-            Self::profiles().data().append_item(script_item.clone())?;
+        // 首次启动或旧版本升级时，使用 `Merge` 和 `Script` 作为全局扩展
+        if Self::profiles().data().global_chain.is_none() {
+            if Self::profiles()
+                .data()
+                .get_item(&"Merge".to_string())
+                .is_err()
+            {
+                let merge_item = PrfItem::from_merge(Some("Merge".to_string()))?;
+                Self::profiles().data().append_item(merge_item.clone())?;
+            }
+            if Self::profiles()
+                .data()
+                .get_item(&"Script".to_string())
+                .is_err()
+            {
+                let script_item = PrfItem::from_script(Some("Script".to_string()))?;
+                Self::profiles().data().append_item(script_item.clone())?;
+            }
+            Self::profiles().data().set_global_chain(vec![
+                GlobalChainItem::new("Merge"),
+                GlobalChainItem::new("Script"),
+            ])?;
         }
         // 生成运行时配置
         if let Err(err) = Self::generate().await {
@@ -169,15 +182,61 @@ impl Config {
 
     /// 生成订阅存好
     pub async fn generate() -> Result<()> {
-        let (config, exists_keys, logs) = enhance::enhance().await;
+        let (input, profile) = EnhanceInput::load();
+        let fingerprint = input.fingerprint(&profile);
+
+        // 订阅、扩展和相关设置都没有变化时，不再重新执行增强流程
+        if Config::runtime().latest().fingerprint == Some(fingerprint) {
+            logging!(
+                info,
+                Type::Config,
+                true,
+                "Enhance inputs unchanged, reuse the runtime config"
+            );
+        } else {
+            Self::enhance_runtime(&input, profile, fingerprint).await;
+        }
+
+        // 系统 DNS 的调整有副作用，复用运行时配置时同样需要执行
+        let config = Config::runtime()
+            .latest()
+            .config
+            .clone()
+            .unwrap_or_default();
+        enhance::sync_tun_dns(&config, input.enable_tun).await;
+
+        Ok(())
+    }
+
+    /// 执行增强流程并写入运行时配置
+    async fn enhance_runtime(input: &EnhanceInput, profile: Mapping, fingerprint: u64) {
+        let (config, exists_keys, logs, trace) = enhance::enhance(input, profile).await;
+
+        // 在配置交给内核之前检查引用缺失、重名等问题
+        let lint = lint_config(&config, &trace);
+        let errors = lint
+            .iter()
+            .filter(|issue| issue.level == LintLevel::Error)
+            .count();
+        if !lint.is_empty() {
+            logging!(
+                warn,
+                Type::Config,
+                true,
+                "Runtime config lint: {} errors, {} warnings",
+                errors,
+                lint.len() - errors
+            );
+        }
 
         *Config::runtime().draft() = Box::new(IRuntime {
             config: Some(config),
             exists_keys,
             chain_logs: logs,
+            chain_trace: trace,
+            lint,
+            fingerprint: Some(fingerprint),
         });
-
-        Ok(())
     }
 }
 
@@ -1,3 +1,4 @@
+mod aggregate;
 mod clash;
 #[allow(clippy::module_inception)]
 mod config;
@@ -9,7 +10,8 @@ mod runtime;
 mod verge;
 
 pub use self::{
-    clash::*, config::*, draft::*, encrypt::*, prfitem::*, profiles::*, runtime::*, verge::*,
+    aggregate::*, clash::*, config::*, draft::*, encrypt::*, prfitem::*, profiles::*, runtime::*,
+    verge::*,
 };
 
 pub const DEFAULT_PAC: &str = r#"function FindProxyForURL(url, host) {
@@ -1,13 +1,18 @@
 use crate::utils::{
+    convert::uri_list::{self, SubscriptionFormat},
     dirs, help,
     network::{NetworkManager, ProxyType},
     tmpl,
 };
 use anyhow::{bail, Context, Result};
 use base64::{engine::general_purpose::STANDARD, Engine as _};
-use reqwest::StatusCode;
+use reqwest::{
+    header::{HeaderMap, HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
 use serde::{Deserialize, Serialize};
 use serde_yaml::Mapping;
+use sha2::{Digest, Sha256};
 use std::{fs, time::Duration};
 use url::Url;
 
@@ -18,7 +23,7 @@ pub struct PrfItem {
     pub uid: Option<String>,
 
     /// profile item type
-    /// enum value: remote | local | script | merge
+    /// enum value: remote | local | aggregate | script | merge | rules | proxies | groups
     #[serde(rename = "type")]
     pub itype: Option<String>,
 
@@ -51,6 +56,11 @@ pub struct PrfItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub option: Option<PrfOption>,
 
+    /// for `remote`, `local` and `aggregate` profile
+    /// uids of the chain items applied to this profile, in order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<Vec<String>>,
+
     /// profile web page url
     #[serde(skip_serializing_if = "Option::is_none")]
     pub home: Option<String>,
@@ -67,6 +77,21 @@ pub struct PrfItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub announce_url: Option<String>,
 
+    /// for `remote` profile
+    /// `ETag` of the last download, sent as `If-None-Match`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+
+    /// for `remote` profile
+    /// `Last-Modified` of the last download, sent as `If-Modified-Since`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+
+    /// for `remote` profile
+    /// sha256 of the conversion template and the file written by the last download
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+
     /// the file data
     #[serde(skip)]
     pub file_data: Option<String>,
@@ -78,6 +103,15 @@ pub struct PrfSelected {
     pub now: Option<String>,
 }
 
+/// 聚合订阅引用的订阅，prefix 加在该订阅的节点名称前
+#[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfSource {
+    pub uid: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+}
+
 #[derive(Default, Debug, Clone, Copy, Deserialize, Serialize)]
 pub struct PrfExtra {
     pub upload: u64,
@@ -133,6 +167,17 @@ pub struct PrfOption {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub update_always: Option<bool>,
+
+    /// for `remote` profile
+    /// template used when the subscription is a list of share links
+    /// default is `tmpl::URI_LIST_TEMPLATE`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri_template: Option<String>,
+
+    /// for `aggregate` profile
+    /// the remote or local profiles merged into this one, in order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<PrfSource>>,
 }
 
 impl PrfOption {
@@ -154,14 +199,39 @@ impl PrfOption {
                 a.timeout_seconds = b.timeout_seconds.or(a.timeout_seconds);
                 a.use_hwid = b.use_hwid.or(a.use_hwid);
                 a.update_always = b.update_always.or(a.update_always);
+                a.uri_template = b.uri_template.or(a.uri_template);
+                a.sources = b.sources.or(a.sources);
                 Some(a)
             }
             t => t.0.or(t.1),
         }
     }
+
+    /// 订阅自带的扩展，按 rules、proxies、groups、merge、script 的顺序
+    pub fn own_chain(&self) -> Vec<String> {
+        [
+            &self.rules,
+            &self.proxies,
+            &self.groups,
+            &self.merge,
+            &self.script,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect()
+    }
 }
 
 impl PrfItem {
+    /// 是否为订阅，其他类型的 item 只能作为扩展使用
+    pub fn is_profile(&self) -> bool {
+        matches!(
+            self.itype.as_deref(),
+            Some("remote" | "local" | "aggregate")
+        )
+    }
+
     /// From partial item
     /// must contain `itype`
     pub async fn from(item: PrfItem, file_data: Option<String>) -> Result<PrfItem> {
@@ -169,7 +239,9 @@ impl PrfItem {
             bail!("type should not be null");
         }
 
-        match item.itype.unwrap().as_str() {
+        // 指定了扩展列表时使用指定的，否则使用订阅自带的扩展
+        let chain = item.chain.clone();
+        let mut item = match item.itype.unwrap().as_str() {
             "remote" => {
                 if item.url.is_none() {
                     bail!("url should not be null");
@@ -177,15 +249,24 @@ impl PrfItem {
                 let url = item.url.as_ref().unwrap().as_str();
                 let name = item.name;
                 let desc = item.desc;
-                PrfItem::from_url(url, name, desc, item.option).await
+                PrfItem::from_url(url, name, desc, item.option, None).await?
             }
             "local" => {
                 let name = item.name.unwrap_or("Local File".into());
                 let desc = item.desc.unwrap_or("".into());
-                PrfItem::from_local(name, desc, file_data, item.option)
+                PrfItem::from_local(name, desc, file_data, item.option)?
+            }
+            "aggregate" => {
+                let name = item.name.unwrap_or("Aggregate".into());
+                let desc = item.desc.unwrap_or("".into());
+                PrfItem::from_aggregate(name, desc, item.option)?
             }
             typ => bail!("invalid profile item type \"{typ}\""),
+        };
+        if chain.is_some() {
+            item.chain = chain;
         }
+        Ok(item)
     }
 
     /// ## Local type
@@ -200,12 +281,21 @@ impl PrfItem {
         let file = format!("{uid}.yaml");
         let opt_ref = option.as_ref();
         let update_interval = opt_ref.and_then(|o| o.update_interval);
+        let uri_template = opt_ref.and_then(|o| o.uri_template.clone());
         let mut merge = opt_ref.and_then(|o| o.merge.clone());
         let mut script = opt_ref.and_then(|o| o.script.clone());
         let mut rules = opt_ref.and_then(|o| o.rules.clone());
         let mut proxies = opt_ref.and_then(|o| o.proxies.clone());
         let mut groups = opt_ref.and_then(|o| o.groups.clone());
 
+        // 导入的 sing-box、SIP008 或分享链接列表文件转换为 Clash 配置
+        let file_data = match file_data {
+            Some(data) => {
+                Some(convert_subscription(&data, uri_template.as_deref())?.unwrap_or(data))
+            }
+            None => None,
+        };
+
         if merge.is_none() {
             let merge_item = PrfItem::from_merge(None)?;
             Config::profiles().data().append_item(merge_item.clone())?;
@@ -233,6 +323,16 @@ impl PrfItem {
             Config::profiles().data().append_item(groups_item.clone())?;
             groups = groups_item.uid;
         }
+        let option = PrfOption {
+            update_interval,
+            merge,
+            script,
+            rules,
+            proxies,
+            groups,
+            uri_template,
+            ..PrfOption::default()
+        };
         Ok(PrfItem {
             uid: Some(uid),
             itype: Some("local".into()),
@@ -242,32 +342,64 @@ impl PrfItem {
             url: None,
             selected: None,
             extra: None,
-            option: Some(PrfOption {
-                update_interval,
-                merge,
-                script,
-                rules,
-                proxies,
-                groups,
-                ..PrfOption::default()
-            }),
+            chain: Some(option.own_chain()),
+            option: Some(option),
             home: None,
             support_url: None,
             announce: None,
             announce_url: None,
+            etag: None,
+            last_modified: None,
+            content_hash: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
             file_data: Some(file_data.unwrap_or(tmpl::ITEM_LOCAL.into())),
         })
     }
 
+    /// ## Aggregate type
+    /// create a new item merging the proxies of `option.sources`
+    pub fn from_aggregate(
+        name: String,
+        desc: String,
+        option: Option<PrfOption>,
+    ) -> Result<PrfItem> {
+        let sources = option
+            .as_ref()
+            .and_then(|o| o.sources.clone())
+            .unwrap_or_default();
+        if sources.is_empty() {
+            bail!("the aggregate profile requires at least one source profile");
+        }
+        let config = Config::profiles().data().aggregate_config(&sources)?;
+        let file_data = serde_yaml::to_string(&config)?;
+
+        // 扩展的创建与本地订阅相同
+        let mut item = PrfItem::from_local(name, desc, Some(file_data), option)?;
+        let uid = help::get_uid("A");
+        item.file = Some(format!("{uid}.yaml"));
+        item.uid = Some(uid);
+        item.itype = Some("aggregate".into());
+        // 聚合订阅随来源订阅更新，没有自己的更新间隔
+        if let Some(option) = item.option.as_mut() {
+            option.update_interval = None;
+            option.uri_template = None;
+            option.sources = Some(sources);
+        }
+        Ok(item)
+    }
+
     /// ## Remote type
     /// create a new item from url
+    ///
+    /// 传入 `cached` 时发送条件请求，内容未变化（304 或相同的内容）时
+    /// 返回的 item 不带 `file_data`，调用方据此跳过写文件和重载内核
     #[allow(clippy::needless_update)]
     pub async fn from_url(
         url: &str,
         name: Option<String>,
         desc: Option<String>,
         option: Option<PrfOption>,
+        cached: Option<&PrfItem>,
     ) -> Result<PrfItem> {
         let opt_ref = option.as_ref();
         let with_proxy = opt_ref.is_some_and(|o| o.with_proxy.unwrap_or(false));
@@ -277,6 +409,7 @@ impl PrfItem {
         let user_agent = opt_ref.and_then(|o| o.user_agent.clone());
         let update_interval = opt_ref.and_then(|o| o.update_interval);
         let timeout = opt_ref.and_then(|o| o.timeout_seconds).unwrap_or(20);
+        let uri_template = opt_ref.and_then(|o| o.uri_template.clone());
         let use_hwid = Config::verge().latest().enable_send_hwid.unwrap_or(true);
         let mut merge = opt_ref.and_then(|o| o.merge.clone());
         let mut script = opt_ref.and_then(|o| o.script.clone());
@@ -293,16 +426,35 @@ impl PrfItem {
             ProxyType::None
         };
 
+        // 本地文件被修改过或转换模板变化时缓存失效，重新下载完整内容
+        let cached = cached.filter(|item| {
+            let hash = item
+                .read_file()
+                .ok()
+                .map(|data| content_hash(&data, uri_template.as_deref()));
+            item.content_hash.is_some() && hash == item.content_hash
+        });
+
         // 使用网络管理器发送请求
-        let resp = match NetworkManager::global()
-            .get_with_interrupt(
-                url,
-                proxy_type,
-                Some(timeout),
-                user_agent.clone(),
-                accept_invalid_certs,
-                use_hwid,
-            )
+        let network = NetworkManager::global();
+        let mut request = network.create_request(
+            url,
+            proxy_type,
+            Some(timeout),
+            user_agent.clone(),
+            accept_invalid_certs,
+            use_hwid,
+        );
+        if let Some(cached) = cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let resp = match network
+            .send_with_interrupt(url, request, Some(timeout))
             .await
         {
             Ok(r) => r,
@@ -313,11 +465,17 @@ impl PrfItem {
         };
 
         let status_code = resp.status();
+        if let Some(cached) = cached.filter(|_| status_code == StatusCode::NOT_MODIFIED) {
+            log::info!(target: "app", "remote profile is not modified: {url}");
+            return Ok(not_modified_item(cached, resp.headers()));
+        }
         if !StatusCode::is_success(&status_code) {
             bail!("failed to fetch remote profile with status {status_code}")
         }
 
         let header = resp.headers();
+        let etag = header_string(header, ETAG);
+        let last_modified = header_string(header, LAST_MODIFIED);
 
         let mut final_url = url.to_string();
 
@@ -335,18 +493,7 @@ impl PrfItem {
         }
 
         // parse the Subscription UserInfo
-        let extra = match header.get("Subscription-Userinfo") {
-            Some(value) => {
-                let sub_info = value.to_str().unwrap_or("");
-                Some(PrfExtra {
-                    upload: help::parse_str(sub_info, "upload").unwrap_or(0),
-                    download: help::parse_str(sub_info, "download").unwrap_or(0),
-                    total: help::parse_str(sub_info, "total").unwrap_or(0),
-                    expire: help::parse_str(sub_info, "expire").unwrap_or(0),
-                })
-            }
-            None => None,
-        };
+        let extra = parse_userinfo(header);
 
         // parse the Content-Disposition
         let filename = match header.get("Content-Disposition") {
@@ -459,12 +606,21 @@ impl PrfItem {
         // process the charset "UTF-8 with BOM"
         let data = data.trim_start_matches('\u{feff}');
 
-        // check the data whether the valid yaml format
-        let yaml = serde_yaml::from_str::<Mapping>(data)
-            .context("the remote profile data is invalid yaml")?;
+        // 识别订阅格式，分享链接列表和 JSON 订阅按模板转换为 Clash 配置
+        let data = match convert_subscription(data, uri_template.as_deref())? {
+            Some(data) => data,
+            None => {
+                // check the data whether the valid yaml format
+                serde_yaml::from_str::<Mapping>(data)
+                    .context("the remote profile data is invalid yaml")?;
+                bail!("profile does not contain `proxies` or `proxy-providers`");
+            }
+        };
 
-        if !yaml.contains_key("proxies") && !yaml.contains_key("proxy-providers") {
-            bail!("profile does not contain `proxies` or `proxy-providers`");
+        let hash = content_hash(&data, uri_template.as_deref());
+        let unchanged = cached.is_some_and(|item| item.content_hash.as_ref() == Some(&hash));
+        if unchanged {
+            log::info!(target: "app", "remote profile content is unchanged: {url}");
         }
 
         if merge.is_none() {
@@ -495,6 +651,27 @@ impl PrfItem {
             groups = groups_item.uid;
         }
 
+        let option = PrfOption {
+            user_agent: user_agent.clone(),
+            with_proxy: if with_proxy { Some(true) } else { None },
+            self_proxy: if self_proxy { Some(true) } else { None },
+            update_interval,
+            update_always,
+            timeout_seconds: Some(timeout),
+            danger_accept_invalid_certs: if accept_invalid_certs {
+                Some(true)
+            } else {
+                None
+            },
+            merge,
+            script,
+            rules,
+            proxies,
+            groups,
+            use_hwid: Some(use_hwid),
+            uri_template,
+            ..PrfOption::default()
+        };
         Ok(PrfItem {
             uid: Some(uid),
             itype: Some("remote".into()),
@@ -504,32 +681,17 @@ impl PrfItem {
             url: Some(final_url),
             selected: None,
             extra,
-            option: Some(PrfOption {
-                user_agent: user_agent.clone(),
-                with_proxy: if with_proxy { Some(true) } else { None },
-                self_proxy: if self_proxy { Some(true) } else { None },
-                update_interval,
-                update_always,
-                timeout_seconds: Some(timeout),
-                danger_accept_invalid_certs: if accept_invalid_certs {
-                    Some(true)
-                } else {
-                    None
-                },
-                merge,
-                script,
-                rules,
-                proxies,
-                groups,
-                use_hwid: Some(use_hwid),
-                ..PrfOption::default()
-            }),
+            chain: Some(option.own_chain()),
+            option: Some(option),
             home,
             support_url,
             announce,
             announce_url,
+            etag,
+            last_modified,
+            content_hash: Some(hash),
             updated: Some(chrono::Local::now().timestamp() as usize),
-            file_data: Some(data.into()),
+            file_data: if unchanged { None } else { Some(data) },
         })
     }
 
@@ -554,10 +716,14 @@ impl PrfItem {
             selected: None,
             extra: None,
             option: None,
+            chain: None,
             home: None,
             support_url: None,
             announce: None,
             announce_url: None,
+            etag: None,
+            last_modified: None,
+            content_hash: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
             file_data: Some(template),
         })
@@ -583,9 +749,13 @@ impl PrfItem {
             support_url: None,
             announce: None,
             announce_url: None,
+            etag: None,
+            last_modified: None,
+            content_hash: None,
             selected: None,
             extra: None,
             option: None,
+            chain: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
             file_data: Some(tmpl::ITEM_SCRIPT.into()),
         })
@@ -607,9 +777,13 @@ impl PrfItem {
             support_url: None,
             announce: None,
             announce_url: None,
+            etag: None,
+            last_modified: None,
+            content_hash: None,
             selected: None,
             extra: None,
             option: None,
+            chain: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
             file_data: Some(tmpl::ITEM_RULES.into()),
         })
@@ -631,9 +805,13 @@ impl PrfItem {
             support_url: None,
             announce: None,
             announce_url: None,
+            etag: None,
+            last_modified: None,
+            content_hash: None,
             selected: None,
             extra: None,
             option: None,
+            chain: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
             file_data: Some(tmpl::ITEM_PROXIES.into()),
         })
@@ -655,9 +833,13 @@ impl PrfItem {
             support_url: None,
             announce: None,
             announce_url: None,
+            etag: None,
+            last_modified: None,
+            content_hash: None,
             selected: None,
             extra: None,
             option: None,
+            chain: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
             file_data: Some(tmpl::ITEM_GROUPS.into()),
         })
@@ -685,3 +867,98 @@ impl PrfItem {
         fs::write(path, data.as_bytes()).context("failed to save the file")
     }
 }
+
+/// 非 Clash 格式的订阅按模板转换，Clash 配置原样返回，无法识别时返回 None
+fn convert_subscription(data: &str, template: Option<&str>) -> Result<Option<String>> {
+    let format = match uri_list::detect_format(data) {
+        Some(SubscriptionFormat::Clash) => return Ok(Some(data.to_string())),
+        Some(format) => format,
+        None => return Ok(None),
+    };
+    let template = template.unwrap_or(tmpl::URI_LIST_TEMPLATE);
+    let converted = uri_list::convert_subscription(data, format, template)?;
+    for skipped in converted.skipped.iter() {
+        log::warn!(target: "app", "skip entry {} ({}) of the subscription: {}", skipped.line, skipped.text, skipped.reason);
+    }
+    Ok(Some(serde_yaml::to_string(&converted.config)?))
+}
+
+/// 订阅文件内容和转换模板的 sha256，用于判断订阅是否有变化
+fn content_hash(data: &str, template: Option<&str>) -> String {
+    let template = template.unwrap_or(tmpl::URI_LIST_TEMPLATE);
+    let mut hasher = Sha256::new();
+    hasher.update(template.as_bytes());
+    hasher.update([0]);
+    hasher.update(data.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// 解析 `Subscription-Userinfo` 中的流量和到期时间
+fn parse_userinfo(headers: &HeaderMap) -> Option<PrfExtra> {
+    let sub_info = headers.get("Subscription-Userinfo")?.to_str().unwrap_or("");
+    Some(PrfExtra {
+        upload: help::parse_str(sub_info, "upload").unwrap_or(0),
+        download: help::parse_str(sub_info, "download").unwrap_or(0),
+        total: help::parse_str(sub_info, "total").unwrap_or(0),
+        expire: help::parse_str(sub_info, "expire").unwrap_or(0),
+    })
+}
+
+/// 订阅未变化（304）时沿用缓存，流量信息和缓存校验头以响应中带的为准
+fn not_modified_item(cached: &PrfItem, headers: &HeaderMap) -> PrfItem {
+    PrfItem {
+        extra: parse_userinfo(headers).or(cached.extra),
+        etag: header_string(headers, ETAG).or_else(|| cached.etag.clone()),
+        last_modified: header_string(headers, LAST_MODIFIED)
+            .or_else(|| cached.last_modified.clone()),
+        updated: Some(chrono::Local::now().timestamp() as usize),
+        file_data: None,
+        ..cached.clone()
+    }
+}
+
+fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
+
+#[test]
+fn test_not_modified_item_refreshes_userinfo() {
+    let cached = PrfItem {
+        uid: Some("r1".into()),
+        etag: Some("\"v1\"".into()),
+        last_modified: Some("Mon, 01 Jan 2024 00:00:00 GMT".into()),
+        extra: Some(PrfExtra {
+            upload: 1,
+            download: 2,
+            total: 100,
+            expire: 0,
+        }),
+        ..PrfItem::default()
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "subscription-userinfo",
+        "upload=10; download=20; total=100; expire=1700000000"
+            .parse()
+            .unwrap(),
+    );
+    headers.insert(ETAG, "\"v2\"".parse().unwrap());
+    let item = not_modified_item(&cached, &headers);
+
+    let extra = item.extra.unwrap();
+    assert_eq!((extra.upload, extra.download), (10, 20));
+    assert_eq!(extra.expire, 1700000000);
+    assert_eq!(item.etag.as_deref(), Some("\"v2\""));
+    // 响应中没有的头沿用缓存
+    assert_eq!(item.last_modified, cached.last_modified);
+    assert_eq!(item.uid, cached.uid);
+    assert!(item.file_data.is_none());
+
+    // 没有带流量信息的 304 保留原有的信息
+    let item = not_modified_item(&cached, &HeaderMap::new());
+    assert_eq!(item.extra.unwrap().download, 2);
+}
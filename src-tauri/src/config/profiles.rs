@@ -1,4 +1,8 @@
-use super::{prfitem::PrfItem, PrfOption};
+use super::{
+    aggregate::{aggregate_config, AggregateSource},
+    prfitem::PrfItem,
+    PrfOption, PrfSource,
+};
 use crate::utils::{dirs, help};
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
@@ -13,6 +17,35 @@ pub struct IProfiles {
 
     /// profile list
     pub items: Option<Vec<PrfItem>>,
+
+    /// 全局扩展，按顺序作用于所有订阅
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub global_chain: Option<Vec<GlobalChainItem>>,
+}
+
+/// 可以作为扩展使用的 item 类型
+pub const CHAIN_TYPES: [&str; 5] = ["merge", "script", "rules", "proxies", "groups"];
+
+/// 全局扩展列表中的一项
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GlobalChainItem {
+    pub uid: String,
+
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl GlobalChainItem {
+    pub fn new(uid: impl Into<String>) -> Self {
+        Self {
+            uid: uid.into(),
+            enabled: true,
+        }
+    }
 }
 
 /// 清理结果
@@ -46,6 +79,7 @@ impl IProfiles {
                         }
                     }
                 }
+                profiles.migrate_chain();
                 profiles
             }
             Err(err) => {
@@ -55,6 +89,30 @@ impl IProfiles {
         }
     }
 
+    /// 旧版本的订阅只通过 option 关联自带的扩展，转换为扩展列表
+    fn migrate_chain(&mut self) {
+        let uids: HashSet<String> = self
+            .items
+            .iter()
+            .flatten()
+            .filter_map(|e| e.uid.clone())
+            .collect();
+        for item in self.items.iter_mut().flatten() {
+            if item.chain.is_some() || !item.is_profile() {
+                continue;
+            }
+            let chain = item
+                .option
+                .as_ref()
+                .map(PrfOption::own_chain)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|uid| uids.contains(uid))
+                .collect();
+            item.chain = Some(chain);
+        }
+    }
+
     pub fn template() -> Self {
         Self {
             items: Some(vec![]),
@@ -136,8 +194,8 @@ impl IProfiles {
                 .with_context(|| format!("failed to write to file \"{file}\""))?;
         }
 
-        if item.itype == Some("remote".to_string()) || item.itype == Some("local".to_string()) {
-            // Always switch current to the newly created remote/local profile
+        if item.is_profile() {
+            // Always switch current to the newly created profile
             self.current = uid.clone();
         }
 
@@ -152,6 +210,34 @@ impl IProfiles {
         self.save_file()
     }
 
+    /// 全局扩展列表，未配置时为空
+    pub fn get_global_chain(&self) -> Vec<GlobalChainItem> {
+        self.global_chain.clone().unwrap_or_default()
+    }
+
+    /// 替换全局扩展列表
+    pub fn set_global_chain(&mut self, chain: Vec<GlobalChainItem>) -> Result<()> {
+        self.check_chain(chain.iter().map(|entry| entry.uid.as_str()))?;
+        self.global_chain = Some(chain);
+        self.save_file()
+    }
+
+    /// 扩展列表只接受扩展类型的 item 且不能重复
+    fn check_chain<'a>(&self, uids: impl Iterator<Item = &'a str>) -> Result<()> {
+        let mut seen = HashSet::new();
+        for uid in uids {
+            if !seen.insert(uid) {
+                bail!("duplicate chain item \"uid:{uid}\"");
+            }
+            let item = self.get_item(&uid.to_string())?;
+            let itype = item.itype.as_deref().unwrap_or_default();
+            if !CHAIN_TYPES.contains(&itype) {
+                bail!("the item \"uid:{uid}\" of type \"{itype}\" can not be used as chain item");
+            }
+        }
+        Ok(())
+    }
+
     /// reorder items
     pub fn reorder(&mut self, active_id: String, over_id: String) -> Result<()> {
         let mut items = self.items.take().unwrap_or_default();
@@ -202,7 +288,7 @@ impl IProfiles {
     }
 
     /// be used to update the remote item
-    /// only patch `updated` `extra` `file_data`
+    /// only patch `updated` `extra` `file_data` and the cache validators
     pub fn update_item(&mut self, uid: String, mut item: PrfItem) -> Result<()> {
         if self.items.is_none() {
             self.items = Some(vec![]);
@@ -224,6 +310,9 @@ impl IProfiles {
                     each.support_url = item.support_url;
                     each.name = item.name;
                     each.url = item.url;
+                    each.etag = item.etag;
+                    each.last_modified = item.last_modified;
+                    each.content_hash = item.content_hash;
                     each.option = PrfOption::merge(each.option.clone(), item.option);
                     // save the file data
                     // move the field value after save
@@ -251,145 +340,233 @@ impl IProfiles {
         self.save_file()
     }
 
-    /// delete item
-    /// if delete the current then return true
-    pub fn delete_item(&mut self, uid: String) -> Result<bool> {
-        let current = self.current.as_ref().unwrap_or(&uid);
-        let current = current.clone();
-        let item = self.get_item(&uid)?;
-        let merge_uid = item.option.as_ref().and_then(|e| e.merge.clone());
-        let script_uid = item.option.as_ref().and_then(|e| e.script.clone());
-        let rules_uid = item.option.as_ref().and_then(|e| e.rules.clone());
-        let proxies_uid = item.option.as_ref().and_then(|e| e.proxies.clone());
-        let groups_uid = item.option.as_ref().and_then(|e| e.groups.clone());
-        let mut items = self.items.take().unwrap_or_default();
-        let mut index = None;
-        let mut merge_index = None;
-        let mut script_index = None;
-        let mut rules_index = None;
-        let mut proxies_index = None;
-        let mut groups_index = None;
-
-        // get the index
-        for (i, _) in items.iter().enumerate() {
-            if items[i].uid == Some(uid.clone()) {
-                index = Some(i);
-                break;
-            }
-        }
-        if let Some(index) = index {
-            if let Some(file) = items.remove(index).file {
-                let _ = dirs::app_profiles_dir().map(|path| {
-                    let path = path.join(file);
-                    if path.exists() {
-                        let _ = fs::remove_file(path);
-                    }
-                });
-            }
-        }
-        // get the merge index
-        for (i, _) in items.iter().enumerate() {
-            if items[i].uid == merge_uid {
-                merge_index = Some(i);
-                break;
-            }
-        }
-        if let Some(index) = merge_index {
-            if let Some(file) = items.remove(index).file {
-                let _ = dirs::app_profiles_dir().map(|path| {
-                    let path = path.join(file);
-                    if path.exists() {
-                        let _ = fs::remove_file(path);
-                    }
-                });
-            }
-        }
-        // get the script index
-        for (i, _) in items.iter().enumerate() {
-            if items[i].uid == script_uid {
-                script_index = Some(i);
-                break;
-            }
-        }
-        if let Some(index) = script_index {
-            if let Some(file) = items.remove(index).file {
-                let _ = dirs::app_profiles_dir().map(|path| {
-                    let path = path.join(file);
-                    if path.exists() {
-                        let _ = fs::remove_file(path);
-                    }
-                });
-            }
+    /// 使用该扩展的订阅 uid，包括扩展列表和订阅自带的扩展
+    pub fn chain_users(&self, uid: &str) -> Vec<String> {
+        self.items
+            .iter()
+            .flatten()
+            .filter(|item| {
+                let in_chain = item
+                    .chain
+                    .as_ref()
+                    .is_some_and(|chain| chain.iter().any(|e| e == uid));
+                let in_option = item
+                    .option
+                    .as_ref()
+                    .is_some_and(|option| option.own_chain().iter().any(|e| e == uid));
+                in_chain || in_option
+            })
+            .filter_map(|item| item.uid.clone())
+            .collect()
+    }
+
+    /// 引用该订阅的聚合订阅 uid
+    pub fn aggregate_users(&self, uid: &str) -> Vec<String> {
+        self.items
+            .iter()
+            .flatten()
+            .filter(|item| {
+                item.option
+                    .as_ref()
+                    .and_then(|option| option.sources.as_ref())
+                    .is_some_and(|sources| sources.iter().any(|source| source.uid == uid))
+            })
+            .filter_map(|item| item.uid.clone())
+            .collect()
+    }
+
+    /// 读取各来源订阅的文件生成聚合订阅的配置，来源只能是远程或本地订阅
+    pub fn aggregate_config(&self, sources: &[PrfSource]) -> Result<Mapping> {
+        let mut aggregate_sources = vec![];
+        for source in sources {
+            let item = match self.get_item(&source.uid) {
+                Ok(item) => item,
+                Err(err) => {
+                    log::warn!(target: "app", "skip the aggregate source: {err}");
+                    continue;
+                }
+            };
+            if !matches!(item.itype.as_deref(), Some("remote" | "local")) {
+                bail!(
+                    "the item \"uid:{}\" can not be used as aggregate source",
+                    source.uid
+                );
+            }
+            let Some(file) = item.file.as_ref() else {
+                continue;
+            };
+            let config = help::read_mapping(&dirs::app_profiles_dir()?.join(file))?;
+            aggregate_sources.push(AggregateSource {
+                name: item.name.clone().unwrap_or(source.uid.clone()),
+                prefix: source.prefix.clone(),
+                config,
+            });
         }
-        // get the rules index
-        for (i, _) in items.iter().enumerate() {
-            if items[i].uid == rules_uid {
-                rules_index = Some(i);
-                break;
-            }
+        aggregate_config(&aggregate_sources)
+    }
+
+    /// 按 option.sources 重新生成聚合订阅的文件
+    pub fn regenerate_aggregate(&mut self, uid: &String) -> Result<()> {
+        let item = self.get_item(uid)?;
+        if item.itype.as_deref() != Some("aggregate") {
+            bail!("the item \"uid:{uid}\" is not an aggregate profile");
+        }
+        let sources = item
+            .option
+            .as_ref()
+            .and_then(|option| option.sources.clone())
+            .unwrap_or_default();
+        let file = item.file.clone().unwrap_or(format!("{uid}.yaml"));
+        let config = self.aggregate_config(&sources)?;
+        help::save_yaml(
+            &dirs::app_profiles_dir()?.join(file),
+            &config,
+            Some("# Aggregate Profile for OutClash"),
+        )?;
+
+        if let Some(item) = self
+            .items
+            .iter_mut()
+            .flatten()
+            .find(|item| item.uid.as_ref() == Some(uid))
+        {
+            item.updated = Some(chrono::Local::now().timestamp() as usize);
         }
-        if let Some(index) = rules_index {
-            if let Some(file) = items.remove(index).file {
-                let _ = dirs::app_profiles_dir().map(|path| {
-                    let path = path.join(file);
-                    if path.exists() {
-                        let _ = fs::remove_file(path);
-                    }
-                });
+        self.save_file()
+    }
+
+    /// 来源订阅更新后重新生成引用它的聚合订阅，返回成功重新生成的 uid
+    pub fn regenerate_aggregates(&mut self, source_uid: &str) -> Vec<String> {
+        let mut regenerated = vec![];
+        for uid in self.aggregate_users(source_uid) {
+            match self.regenerate_aggregate(&uid) {
+                Ok(()) => regenerated.push(uid),
+                Err(err) => {
+                    log::warn!(target: "app", "failed to regenerate the aggregate profile \"uid:{uid}\": {err}")
+                }
             }
         }
-        // get the proxies index
-        for (i, _) in items.iter().enumerate() {
-            if items[i].uid == proxies_uid {
-                proxies_index = Some(i);
-                break;
-            }
+        regenerated
+    }
+
+    /// 修改订阅的扩展列表，扩展可以被多个订阅共用
+    pub fn set_profile_chain(&mut self, uid: String, chain: Vec<String>) -> Result<()> {
+        let item = self.get_item(&uid)?;
+        if !item.is_profile() {
+            bail!("the item \"uid:{uid}\" is not a profile");
         }
-        if let Some(index) = proxies_index {
-            if let Some(file) = items.remove(index).file {
-                let _ = dirs::app_profiles_dir().map(|path| {
-                    let path = path.join(file);
-                    if path.exists() {
-                        let _ = fs::remove_file(path);
-                    }
-                });
-            }
+        self.check_chain(chain.iter().map(String::as_str))?;
+
+        if let Some(item) = self
+            .items
+            .iter_mut()
+            .flatten()
+            .find(|item| item.uid.as_ref() == Some(&uid))
+        {
+            item.chain = Some(chain);
         }
-        // get the groups index
-        for (i, _) in items.iter().enumerate() {
-            if items[i].uid == groups_uid {
-                groups_index = Some(i);
-                break;
+        self.save_file()
+    }
+
+    /// delete item
+    /// if delete the current then return true
+    ///
+    /// 删除订阅时，其引用的扩展只有在没有其他订阅和全局扩展使用时才会一并删除；
+    /// 仍被订阅使用的扩展不能直接删除
+    pub fn delete_item(&mut self, uid: String) -> Result<bool> {
+        let current = self.current.as_ref().unwrap_or(&uid);
+        let current = current.clone();
+        let item = self.get_item(&uid)?;
+
+        let aggregates = self.aggregate_users(&uid);
+        if !aggregates.is_empty() {
+            bail!(
+                "the profile \"uid:{uid}\" is still used by aggregate profiles: {}",
+                aggregates.join(", ")
+            );
+        }
+
+        let mut linked = vec![];
+        if item.is_profile() {
+            linked.extend(item.chain.clone().unwrap_or_default());
+            linked.extend(
+                item.option
+                    .as_ref()
+                    .map(PrfOption::own_chain)
+                    .unwrap_or_default(),
+            );
+        } else {
+            let users = self.chain_users(&uid);
+            if !users.is_empty() {
+                bail!(
+                    "the item \"uid:{uid}\" is still used by profiles: {}",
+                    users.join(", ")
+                );
             }
         }
-        if let Some(index) = groups_index {
-            if let Some(file) = items.remove(index).file {
-                let _ = dirs::app_profiles_dir().map(|path| {
-                    let path = path.join(file);
-                    if path.exists() {
-                        let _ = fs::remove_file(path);
-                    }
-                });
+
+        let mut items = self.items.take().unwrap_or_default();
+        Self::remove_item(&mut items, &uid);
+        self.items = Some(items);
+
+        let mut seen = HashSet::new();
+        for linked_uid in linked {
+            let in_global = self
+                .global_chain
+                .iter()
+                .flatten()
+                .any(|entry| entry.uid == linked_uid);
+            if seen.insert(linked_uid.clone())
+                && !in_global
+                && self.chain_users(&linked_uid).is_empty()
+            {
+                if let Some(items) = self.items.as_mut() {
+                    Self::remove_item(items, &linked_uid);
+                }
             }
         }
+
+        let items = self.items.take().unwrap_or_default();
         // delete the original uid
         if current == uid {
             self.current = None;
             for item in items.iter() {
-                if item.itype == Some("remote".to_string())
-                    || item.itype == Some("local".to_string())
-                {
+                if item.is_profile() {
                     self.current = item.uid.clone();
                     break;
                 }
             }
         }
 
+        // 已删除的 item 同时移出全局扩展
+        if let Some(chain) = self.global_chain.as_mut() {
+            chain.retain(|entry| {
+                items
+                    .iter()
+                    .any(|item| item.uid.as_ref() == Some(&entry.uid))
+            });
+        }
+
         self.items = Some(items);
         self.save_file()?;
         Ok(current == uid)
     }
 
+    /// 从列表中移除 item 并删除对应的文件
+    fn remove_item(items: &mut Vec<PrfItem>, uid: &str) {
+        let Some(index) = items.iter().position(|e| e.uid.as_deref() == Some(uid)) else {
+            return;
+        };
+        if let Some(file) = items.remove(index).file {
+            let _ = dirs::app_profiles_dir().map(|path| {
+                let path = path.join(file);
+                if path.exists() {
+                    let _ = fs::remove_file(path);
+                }
+            });
+        }
+    }
+
     /// 获取current指向的订阅内容
     pub fn current_mapping(&self) -> Result<Mapping> {
         match (self.current.as_ref(), self.items.as_ref()) {
@@ -407,74 +584,13 @@ impl IProfiles {
         }
     }
 
-    /// 获取current指向的订阅的merge
-    pub fn current_merge(&self) -> Option<String> {
-        match (self.current.as_ref(), self.items.as_ref()) {
-            (Some(current), Some(items)) => {
-                if let Some(item) = items.iter().find(|e| e.uid.as_ref() == Some(current)) {
-                    let merge = item.option.as_ref().and_then(|e| e.merge.clone());
-                    return merge;
-                }
-                None
-            }
-            _ => None,
-        }
-    }
-
-    /// 获取current指向的订阅的script
-    pub fn current_script(&self) -> Option<String> {
-        match (self.current.as_ref(), self.items.as_ref()) {
-            (Some(current), Some(items)) => {
-                if let Some(item) = items.iter().find(|e| e.uid.as_ref() == Some(current)) {
-                    let script = item.option.as_ref().and_then(|e| e.script.clone());
-                    return script;
-                }
-                None
-            }
-            _ => None,
-        }
-    }
-
-    /// 获取current指向的订阅的rules
-    pub fn current_rules(&self) -> Option<String> {
-        match (self.current.as_ref(), self.items.as_ref()) {
-            (Some(current), Some(items)) => {
-                if let Some(item) = items.iter().find(|e| e.uid.as_ref() == Some(current)) {
-                    let rules = item.option.as_ref().and_then(|e| e.rules.clone());
-                    return rules;
-                }
-                None
-            }
-            _ => None,
-        }
-    }
-
-    /// 获取current指向的订阅的proxies
-    pub fn current_proxies(&self) -> Option<String> {
-        match (self.current.as_ref(), self.items.as_ref()) {
-            (Some(current), Some(items)) => {
-                if let Some(item) = items.iter().find(|e| e.uid.as_ref() == Some(current)) {
-                    let proxies = item.option.as_ref().and_then(|e| e.proxies.clone());
-                    return proxies;
-                }
-                None
-            }
-            _ => None,
-        }
-    }
-
-    /// 获取current指向的订阅的groups
-    pub fn current_groups(&self) -> Option<String> {
-        match (self.current.as_ref(), self.items.as_ref()) {
-            (Some(current), Some(items)) => {
-                if let Some(item) = items.iter().find(|e| e.uid.as_ref() == Some(current)) {
-                    let groups = item.option.as_ref().and_then(|e| e.groups.clone());
-                    return groups;
-                }
-                None
-            }
-            _ => None,
-        }
+    /// 获取current指向的订阅的扩展列表
+    pub fn current_chain(&self) -> Vec<String> {
+        self.current
+            .as_ref()
+            .and_then(|current| self.get_item(current).ok())
+            .and_then(|item| item.chain.clone())
+            .unwrap_or_default()
     }
 
     /// 判断profile是否是current指向的
@@ -594,9 +710,9 @@ impl IProfiles {
                     active_files.insert(file.clone());
                 }
 
-                // 对于主 profile 类型（remote/local），还需要收集其关联的扩展文件
+                // 对于主 profile 类型（remote/local/aggregate），还需要收集其关联的扩展文件
                 if let Some(itype) = &item.itype {
-                    if itype == "remote" || itype == "local" {
+                    if itype == "remote" || itype == "local" || itype == "aggregate" {
                         if let Some(option) = &item.option {
                             // 收集关联的扩展文件
                             if let Some(merge_uid) = &option.merge {
@@ -652,6 +768,7 @@ impl IProfiles {
         // 匹配各种 profile 文件格式
         // R12345678.yaml (remote)
         // L12345678.yaml (local)
+        // A12345678.yaml (aggregate)
         // m12345678.yaml (merge)
         // s12345678.js (script)
         // r12345678.yaml (rules)
@@ -659,7 +776,7 @@ impl IProfiles {
         // g12345678.yaml (groups)
 
         let patterns = [
-            r"^[RL][a-zA-Z0-9]+\.yaml$",  // Remote/Local profiles
+            r"^[RLA][a-zA-Z0-9]+\.yaml$", // Remote/Local/Aggregate profiles
             r"^m[a-zA-Z0-9]+\.yaml$",     // Merge files
             r"^s[a-zA-Z0-9]+\.js$",       // Script files
             r"^[rpg][a-zA-Z0-9]+\.yaml$", // Rules/Proxies/Groups files
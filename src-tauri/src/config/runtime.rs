@@ -1,4 +1,4 @@
-use crate::enhance::field::use_keys;
+use crate::enhance::{field::use_keys, lint::LintIssue, trace::EnhanceStep};
 use serde::{Deserialize, Serialize};
 use serde_yaml::{Mapping, Value};
 use std::collections::HashMap;
@@ -9,6 +9,15 @@ pub struct IRuntime {
     // 这些keys不一定都生效
     pub exists_keys: Vec<String>,
     pub chain_logs: HashMap<String, Vec<(String, String)>>,
+    // 增强流程每一步对配置的修改
+    #[serde(default)]
+    pub chain_trace: Vec<EnhanceStep>,
+    // 增强后配置的检查结果
+    #[serde(default)]
+    pub lint: Vec<LintIssue>,
+    // 生成该配置时的输入指纹，输入不变时沿用该配置
+    #[serde(skip)]
+    pub fingerprint: Option<u64>,
 }
 
 impl IRuntime {
@@ -4,7 +4,7 @@ use crate::{
         handle,
         service::{self},
     },
-    logging, logging_error,
+    feat, logging, logging_error,
     module::mihomo::MihomoManager,
     utils::{
         dirs,
@@ -142,6 +142,9 @@ impl CoreManager {
             config: Some(Config::clash().latest().0.clone()),
             exists_keys: vec![],
             chain_logs: Default::default(),
+            chain_trace: Default::default(),
+            lint: Default::default(),
+            fingerprint: None,
         });
         help::save_yaml(
             &runtime_path,
@@ -469,7 +472,10 @@ impl CoreManager {
                 // 4. 验证通过后，生成正式的运行时配置
                 logging!(info, Type::Config, true, "Generating runtime configuration");
                 let run_path = Config::generate_file(ConfigType::Run)?;
-                logging_error!(Type::Config, true, self.put_configs_force(run_path).await);
+                if self.put_configs_force(run_path).await.is_ok() {
+                    // 重新加载后内核会重置代理组选择
+                    feat::restore_proxy_selections_later();
+                }
                 Ok((true, "something".into()))
             }
             Ok((false, error_msg)) => {
@@ -1231,6 +1237,7 @@ impl CoreManager {
                 self.start_core_by_sidecar().await?;
             }
         }
+        MihomoManager::reset_circuit_breaker();
         Ok(())
     }
 
@@ -1248,6 +1255,7 @@ impl CoreManager {
         self.stop_core().await?;
 
         self.start_core().await?;
+        feat::restore_proxy_selections_later();
         Ok(())
     }
 
@@ -23,6 +23,8 @@ enum FrontendEvent {
     StartupCompleted,
     ProfileUpdateStarted { uid: String },
     ProfileUpdateCompleted { uid: String },
+    DelayTestProgress { payload: serde_json::Value },
+    ClashStream { payload: serde_json::Value },
 }
 
 /// 事件发送统计和监控
@@ -145,6 +147,12 @@ impl NotificationSystem {
                                         FrontendEvent::ProfileUpdateCompleted { uid } => {
                                             ("profile-update-completed", Ok(serde_json::json!({ "uid": uid })))
                                         }
+                                        FrontendEvent::DelayTestProgress { payload } => {
+                                            ("verge://delay-test-progress", Ok(payload))
+                                        }
+                                        FrontendEvent::ClashStream { payload } => {
+                                            ("verge://clash-stream", Ok(payload))
+                                        }
                                     };
 
                                     if let Ok(payload) = payload_result {
@@ -412,6 +420,45 @@ impl Handle {
         }
     }
 
+    /// 批量延迟测试进度
+    pub fn notify_delay_test_progress<T: serde::Serialize>(progress: &T) {
+        let handle = Self::global();
+        if handle.is_exiting() {
+            return;
+        }
+
+        let payload = match serde_json::to_value(progress) {
+            Ok(payload) => payload,
+            Err(e) => {
+                log::error!("Failed to serialize DelayTestProgress payload: {e}");
+                return;
+            }
+        };
+
+        let system_opt = handle.notification_system.read();
+        if let Some(system) = system_opt.as_ref() {
+            system.send_event(FrontendEvent::DelayTestProgress { payload });
+        } else {
+            log::warn!(
+                "Notification system not initialized when trying to send DelayTestProgress event."
+            );
+        }
+    }
+
+    /// 转发后端订阅的控制器推送，前端按 `id` 区分订阅
+    pub fn notify_clash_stream(id: u32, data: serde_json::Value) {
+        let handle = Self::global();
+        if handle.is_exiting() {
+            return;
+        }
+
+        let payload = serde_json::json!({ "id": id, "data": data });
+        let system_opt = handle.notification_system.read();
+        if let Some(system) = system_opt.as_ref() {
+            system.send_event(FrontendEvent::ClashStream { payload });
+        }
+    }
+
     /// 通知前端显示消息队列
     pub fn notice_message<S: Into<String>, M: Into<String>>(status: S, msg: M) {
         let handle = Self::global();
@@ -0,0 +1,104 @@
+var verge = (function () {
+  function deepFreeze(value) {
+    if (value && typeof value === "object" && !Object.isFrozen(value)) {
+      Object.freeze(value);
+      Object.keys(value).forEach((key) => deepFreeze(value[key]));
+    }
+    return value;
+  }
+
+  function toRegExp(pattern, flags) {
+    if (pattern instanceof RegExp) {
+      // 去掉 g / y，避免 test() 受 lastIndex 影响
+      return new RegExp(pattern.source, pattern.flags.replace(/[gy]/g, ""));
+    }
+    return new RegExp(String(pattern), flags || "");
+  }
+
+  function nameOf(proxy) {
+    return typeof proxy === "string" ? proxy : proxy && proxy.name;
+  }
+
+  function renameOne(proxy, re, replacement) {
+    if (typeof proxy === "string") return proxy.replace(re, replacement);
+    if (proxy && typeof proxy.name === "string") {
+      return Object.assign({}, proxy, {
+        name: proxy.name.replace(re, replacement),
+      });
+    }
+    return proxy;
+  }
+
+  const proxies = {
+    names(list) {
+      return (list || []).map(nameOf);
+    },
+    filter(list, pattern, flags) {
+      const re = toRegExp(pattern, flags);
+      return (list || []).filter((p) => re.test(nameOf(p) || ""));
+    },
+    exclude(list, pattern, flags) {
+      const re = toRegExp(pattern, flags);
+      return (list || []).filter((p) => !re.test(nameOf(p) || ""));
+    },
+    rename(list, pattern, replacement, flags) {
+      const re = toRegExp(pattern, flags);
+      return (list || []).map((p) => renameOne(p, re, replacement));
+    },
+    // 同时修改 proxies 与各代理组中的引用，DIRECT、REJECT 和其他代理组名保持不变
+    renameInConfig(config, pattern, replacement, flags) {
+      const re = toRegExp(pattern, flags);
+      const renamed = new Map();
+      if (Array.isArray(config.proxies)) {
+        config.proxies = config.proxies.map((p) => {
+          const next = renameOne(p, re, replacement);
+          const name = nameOf(p);
+          if (typeof name === "string") renamed.set(name, nameOf(next));
+          return next;
+        });
+      }
+      if (Array.isArray(config["proxy-groups"])) {
+        config["proxy-groups"].forEach((group) => {
+          if (Array.isArray(group.proxies)) {
+            group.proxies = group.proxies.map((name) =>
+              renamed.has(name) ? renamed.get(name) : name,
+            );
+          }
+        });
+      }
+      return config;
+    },
+  };
+
+  return deepFreeze({
+    version: "1.0.0",
+    yaml: {
+      parse(text) {
+        return JSON.parse(__verge_yaml_parse__(String(text)));
+      },
+      stringify(value) {
+        return __verge_yaml_stringify__(JSON.stringify(value));
+      },
+    },
+    base64: {
+      encode(text) {
+        return __verge_base64_encode__(String(text));
+      },
+      decode(text) {
+        return __verge_base64_decode__(String(text));
+      },
+    },
+    url: {
+      parse(text) {
+        return JSON.parse(__verge_url_parse__(String(text)));
+      },
+    },
+    proxies,
+    profiles: {
+      // 按名称或 uid 读取其他订阅的节点列表，返回只读数组，找不到时为 null
+      proxies(name) {
+        return deepFreeze(JSON.parse(__verge_profile_proxies__(String(name))));
+      },
+    },
+  });
+})();
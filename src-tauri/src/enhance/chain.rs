@@ -6,13 +6,13 @@ use crate::{
 use serde_yaml::Mapping;
 use std::fs;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct ChainItem {
     pub uid: String,
     pub data: ChainType,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub enum ChainType {
     Merge(Mapping),
     Script(String),
@@ -21,6 +21,19 @@ pub enum ChainType {
     Groups(SeqMap),
 }
 
+impl ChainType {
+    /// 扩展类型名，与 item 的 `type` 一致
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ChainType::Merge(_) => "merge",
+            ChainType::Script(_) => "script",
+            ChainType::Rules(_) => "rules",
+            ChainType::Proxies(_) => "proxies",
+            ChainType::Groups(_) => "groups",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ChainSupport {
     Clash,
@@ -1,7 +1,6 @@
 use serde_yaml::{Mapping, Value};
-use std::collections::HashSet;
 
-pub const HANDLE_FIELDS: [&str; 12] = [
+pub const HANDLE_FIELDS: [&str; 13] = [
     "mode",
     "redir-port",
     "tproxy-port",
@@ -12,6 +11,7 @@ pub const HANDLE_FIELDS: [&str; 12] = [
     "log-level",
     "ipv6",
     "external-controller",
+    "external-controller-unix",
     "secret",
     "unified-delay",
 ];
@@ -37,32 +37,26 @@ pub fn use_lowercase(config: Mapping) -> Mapping {
     ret
 }
 
-pub fn use_sort(config: Mapping) -> Mapping {
-    let mut ret = Mapping::new();
+/// 按 HANDLE_FIELDS、其他字段、DEFAULT_FIELDS 的顺序排列，其他字段保持原有顺序
+pub fn use_sort(mut config: Mapping) -> Mapping {
+    let mut ret = Mapping::with_capacity(config.len());
     HANDLE_FIELDS.into_iter().for_each(|key| {
-        let key = Value::from(key);
-        if let Some(value) = config.get(&key) {
-            ret.insert(key, value.clone());
+        if let Some(value) = config.shift_remove(key) {
+            ret.insert(Value::from(key), value);
         }
     });
 
-    let supported_keys: HashSet<&str> = HANDLE_FIELDS.into_iter().chain(DEFAULT_FIELDS).collect();
-
-    let config_keys: HashSet<&str> = config.keys().filter_map(|e| e.as_str()).collect();
-
-    config_keys.difference(&supported_keys).for_each(|&key| {
-        let key = Value::from(key);
-        if let Some(value) = config.get(&key) {
-            ret.insert(key, value.clone());
-        }
-    });
+    let mut defaults = Mapping::new();
     DEFAULT_FIELDS.into_iter().for_each(|key| {
-        let key = Value::from(key);
-        if let Some(value) = config.get(&key) {
-            ret.insert(key, value.clone());
+        if let Some(value) = config.shift_remove(key) {
+            defaults.insert(Value::from(key), value);
         }
     });
 
+    // 其他字段只保留字符串键
+    ret.extend(config.into_iter().filter(|(key, _)| key.is_string()));
+    ret.extend(defaults);
+
     ret
 }
 
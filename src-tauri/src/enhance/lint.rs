@@ -0,0 +1,493 @@
+use super::trace::{display_value, EnhanceStep};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+
+/// 内核内置的出站，规则和代理组可以直接引用
+pub const BUILTIN_OUTBOUNDS: [&str; 6] = [
+    "DIRECT",
+    "REJECT",
+    "REJECT-DROP",
+    "PASS",
+    "COMPATIBLE",
+    "GLOBAL",
+];
+
+/// 逻辑规则，payload 是括号包裹的子规则列表
+pub const LOGIC_RULES: [&str; 4] = ["AND", "OR", "NOT", "SUB-RULE"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Error,
+    Warning,
+}
+
+/// 一条检查结果，step 和 uid 指向引入该问题的增强步骤
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LintIssue {
+    pub level: LintLevel,
+    pub kind: String,
+    pub path: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+}
+
+/// 拆开后的一条规则，内层规则没有 target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleParts<'a> {
+    pub kind: String,
+    pub payload: &'a str,
+    pub target: Option<&'a str>,
+    pub params: Vec<&'a str>,
+}
+
+/// 拆分规则，逻辑规则的 payload 保留括号；MATCH 没有 payload
+pub fn split_rule(rule: &str, with_target: bool) -> Option<RuleParts<'_>> {
+    let (kind, rest) = match rule.split_once(',') {
+        Some((kind, rest)) => (kind.trim().to_uppercase(), rest),
+        None => (rule.trim().to_uppercase(), ""),
+    };
+    if kind.is_empty() {
+        return None;
+    }
+
+    let parts = if LOGIC_RULES.contains(&kind.as_str()) {
+        split_top(rest)
+    } else {
+        rest.split(',').map(str::trim).collect()
+    };
+    let mut parts = parts.into_iter().filter(|part| !part.is_empty());
+
+    let payload = match kind.as_str() {
+        "MATCH" => "",
+        _ => parts.next()?,
+    };
+    let target = match with_target {
+        true => Some(parts.next()?),
+        false => None,
+    };
+    Some(RuleParts {
+        kind,
+        payload,
+        target,
+        params: parts.collect(),
+    })
+}
+
+/// 逻辑规则 payload 中的子规则，去掉各自的括号；SUB-RULE 的条件只有一条规则
+pub fn logic_operands(payload: &str) -> Vec<&str> {
+    let inner = payload
+        .trim()
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .unwrap_or_default();
+    if !inner.starts_with('(') {
+        return match inner.is_empty() {
+            true => vec![],
+            false => vec![inner],
+        };
+    }
+    split_top(inner)
+        .into_iter()
+        .filter_map(|part| part.strip_prefix('(')?.strip_suffix(')'))
+        .collect()
+}
+
+/// 按不在括号内的逗号拆分
+fn split_top(text: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts
+}
+
+/// 检查增强后的配置，引用缺失、重名、不可达的规则等问题只有内核检查或运行时才会暴露
+pub fn lint_config(config: &Mapping, trace: &[EnhanceStep]) -> Vec<LintIssue> {
+    let mut linter = Linter {
+        trace,
+        issues: vec![],
+    };
+
+    let proxies = list_names(config, "proxies");
+    let groups = list_names(config, "proxy-groups");
+    let providers = map_keys(config, "proxy-providers");
+    let rule_providers = map_keys(config, "rule-providers");
+    let sub_rules = map_keys(config, "sub-rules");
+
+    let mut seen = HashSet::new();
+    for name in proxies.iter() {
+        if !seen.insert(name.as_str()) {
+            linter.push(
+                LintLevel::Error,
+                "duplicate-name",
+                format!("proxies.{name}"),
+                format!("proxy name `{name}` is used more than once"),
+                linter.inserted("proxies", &named(name)),
+            );
+        }
+    }
+    for name in groups.iter() {
+        if !seen.insert(name.as_str()) {
+            linter.push(
+                LintLevel::Error,
+                "duplicate-name",
+                format!("proxy-groups.{name}"),
+                format!("proxy group name `{name}` is already used by a proxy or group"),
+                linter.inserted("proxy-groups", &named(name)),
+            );
+        }
+    }
+
+    let outbounds: HashSet<&str> = proxies
+        .iter()
+        .chain(groups.iter())
+        .map(String::as_str)
+        .chain(BUILTIN_OUTBOUNDS)
+        .collect();
+
+    let group_list = config.get("proxy-groups").and_then(Value::as_sequence);
+    for group in group_list
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_mapping)
+    {
+        let Some(name) = group.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        let members = group.get("proxies").and_then(Value::as_sequence);
+        let uses = group.get("use").and_then(Value::as_sequence);
+
+        for member in members.into_iter().flatten().filter_map(Value::as_str) {
+            if !outbounds.contains(member) {
+                let origin = linter
+                    .deleted("proxies", &named(member))
+                    .or_else(|| linter.inserted("proxy-groups", &named(name)));
+                linter.push(
+                    LintLevel::Error,
+                    "missing-proxy",
+                    format!("proxy-groups.{name}"),
+                    format!("group `{name}` references missing proxy `{member}`"),
+                    origin,
+                );
+            }
+        }
+        for provider in uses.into_iter().flatten().filter_map(Value::as_str) {
+            if !providers.contains(provider) {
+                linter.push(
+                    LintLevel::Error,
+                    "missing-provider",
+                    format!("proxy-groups.{name}"),
+                    format!("group `{name}` uses undefined proxy provider `{provider}`"),
+                    linter.inserted("proxy-groups", &named(name)),
+                );
+            }
+        }
+
+        let include_all = [
+            "include-all",
+            "include-all-proxies",
+            "include-all-providers",
+        ]
+        .iter()
+        .any(|key| group.get(*key).and_then(Value::as_bool).unwrap_or(false));
+        let is_empty = |list: Option<&Vec<Value>>| list.is_none_or(|list| list.is_empty());
+        if !include_all && is_empty(members) && is_empty(uses) {
+            linter.push(
+                LintLevel::Warning,
+                "empty-group",
+                format!("proxy-groups.{name}"),
+                format!("group `{name}` has no proxies"),
+                linter.inserted("proxy-groups", &named(name)),
+            );
+        }
+    }
+
+    let context = RuleContext {
+        outbounds: &outbounds,
+        rule_providers: &rule_providers,
+        sub_rules: &sub_rules,
+    };
+    linter.lint_rules("rules", config.get("rules"), &context);
+    if let Some(Value::Mapping(map)) = config.get("sub-rules") {
+        for (name, rules) in map.iter() {
+            if let Some(name) = name.as_str() {
+                linter.lint_rules(&format!("sub-rules.{name}"), Some(rules), &context);
+            }
+        }
+    }
+
+    linter.issues
+}
+
+struct RuleContext<'a> {
+    outbounds: &'a HashSet<&'a str>,
+    rule_providers: &'a HashSet<String>,
+    sub_rules: &'a HashSet<String>,
+}
+
+struct Linter<'a> {
+    trace: &'a [EnhanceStep],
+    issues: Vec<LintIssue>,
+}
+
+impl<'a> Linter<'a> {
+    fn push(
+        &mut self,
+        level: LintLevel,
+        kind: &str,
+        path: String,
+        message: String,
+        origin: Option<&EnhanceStep>,
+    ) {
+        self.issues.push(LintIssue {
+            level,
+            kind: kind.into(),
+            path,
+            message,
+            step: origin.map(|step| step.step.clone()),
+            uid: origin.and_then(|step| step.uid.clone()),
+        });
+    }
+
+    /// 最后一个插入该项（或新增整个列表）的步骤，找不到说明来自订阅本身
+    fn inserted(&self, path: &str, value: &str) -> Option<&'a EnhanceStep> {
+        self.trace.iter().rev().find(|step| {
+            step.diff
+                .inserted
+                .iter()
+                .any(|change| change.path == path && change.value == value)
+                || step.diff.added.iter().any(|added| added == path)
+        })
+    }
+
+    /// 最后一个删除该项的步骤
+    fn deleted(&self, path: &str, value: &str) -> Option<&'a EnhanceStep> {
+        self.trace.iter().rev().find(|step| {
+            step.diff
+                .deleted
+                .iter()
+                .any(|change| change.path == path && change.value == value)
+        })
+    }
+
+    fn lint_rules(&mut self, path: &str, rules: Option<&Value>, context: &RuleContext) {
+        let rules = rules.and_then(Value::as_sequence).into_iter().flatten();
+        let mut matched: Option<usize> = None;
+
+        for (index, rule) in rules.filter_map(Value::as_str).enumerate() {
+            let rule_path = format!("{path}.{index}");
+            let display = display_value(&Value::String(rule.into()));
+
+            if let Some(at) = matched {
+                let origin = self.inserted(path, &display);
+                self.push(
+                    LintLevel::Warning,
+                    "unreachable-rule",
+                    rule_path,
+                    format!("rule `{rule}` comes after MATCH at index {at} and never matches"),
+                    origin,
+                );
+                continue;
+            }
+
+            let Some(parts) = split_rule(rule, true) else {
+                let origin = self.inserted(path, &display);
+                self.push(
+                    LintLevel::Error,
+                    "invalid-rule",
+                    rule_path,
+                    format!("rule `{rule}` is missing a payload or target"),
+                    origin,
+                );
+                continue;
+            };
+            if parts.kind == "MATCH" {
+                matched = Some(index);
+            }
+
+            let target = parts.target.unwrap_or_default();
+            let missing_target = match parts.kind.as_str() {
+                "SUB-RULE" => (!context.sub_rules.contains(target))
+                    .then(|| format!("rule `{rule}` targets undefined sub-rule `{target}`")),
+                _ => (!context.outbounds.contains(target))
+                    .then(|| format!("rule `{rule}` targets missing proxy or group `{target}`")),
+            };
+            if let Some(message) = missing_target {
+                let origin = self.inserted(path, &display);
+                self.push(
+                    LintLevel::Error,
+                    "missing-target",
+                    rule_path.clone(),
+                    message,
+                    origin,
+                );
+            }
+
+            for set in rule_sets(&parts) {
+                if !context.rule_providers.contains(set) {
+                    let origin = self.inserted(path, &display);
+                    self.push(
+                        LintLevel::Error,
+                        "missing-rule-provider",
+                        rule_path.clone(),
+                        format!("rule `{rule}` uses undefined rule provider `{set}`"),
+                        origin,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// 规则（包括逻辑规则的子规则）中引用的 rule-provider
+fn rule_sets<'a>(parts: &RuleParts<'a>) -> Vec<&'a str> {
+    match parts.kind.as_str() {
+        "RULE-SET" => vec![parts.payload],
+        kind if LOGIC_RULES.contains(&kind) => logic_operands(parts.payload)
+            .into_iter()
+            .filter_map(|operand| split_rule(operand, false))
+            .flat_map(|inner| rule_sets(&inner))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn named(name: &str) -> String {
+    format!("name: {name}")
+}
+
+fn list_names(config: &Mapping, key: &str) -> Vec<String> {
+    config
+        .get(key)
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.get("name")?.as_str().map(str::to_string))
+        .collect()
+}
+
+fn map_keys(config: &Mapping, key: &str) -> HashSet<String> {
+    config
+        .get(key)
+        .and_then(Value::as_mapping)
+        .into_iter()
+        .flat_map(|map| map.keys())
+        .filter_map(|key| key.as_str().map(str::to_string))
+        .collect()
+}
+
+#[test]
+fn test_split_rule() {
+    let parts = split_rule("DOMAIN-SUFFIX,google.com,Proxy", true).unwrap();
+    assert_eq!(parts.kind, "DOMAIN-SUFFIX");
+    assert_eq!(parts.payload, "google.com");
+    assert_eq!(parts.target, Some("Proxy"));
+
+    let parts = split_rule("IP-CIDR,1.1.1.1/32,DIRECT,no-resolve", true).unwrap();
+    assert_eq!(parts.params, vec!["no-resolve"]);
+
+    let parts = split_rule("match,DIRECT", true).unwrap();
+    assert_eq!(parts.kind, "MATCH");
+    assert_eq!(parts.target, Some("DIRECT"));
+
+    let parts = split_rule("AND,((DOMAIN,a.com),(NOT,((NETWORK,UDP)))),REJECT", true).unwrap();
+    assert_eq!(parts.payload, "((DOMAIN,a.com),(NOT,((NETWORK,UDP))))");
+    assert_eq!(parts.target, Some("REJECT"));
+    assert_eq!(
+        logic_operands(parts.payload),
+        vec!["DOMAIN,a.com", "NOT,((NETWORK,UDP))"]
+    );
+    assert_eq!(logic_operands("(DST-PORT,443)"), vec!["DST-PORT,443"]);
+
+    assert_eq!(split_rule("DOMAIN,a.com", true), None);
+}
+
+#[test]
+fn test_lint_config() {
+    use super::trace::EnhanceTrace;
+
+    let before = serde_yaml::from_str::<Mapping>(
+        r#"
+        proxies:
+          - { name: a, type: ss }
+          - { name: b, type: ss }
+        proxy-groups:
+          - { name: Proxy, type: select, proxies: [a, b] }
+        rules:
+          - MATCH,Proxy
+        "#,
+    )
+    .unwrap();
+    let after = serde_yaml::from_str::<Mapping>(
+        r#"
+        proxies:
+          - { name: a, type: ss }
+          - { name: a, type: vmess }
+        proxy-groups:
+          - { name: Proxy, type: select, proxies: [a, b] }
+          - { name: Auto, type: url-test, use: [remote] }
+          - { name: Empty, type: select }
+          - { name: All, type: select, include-all: true }
+        rule-providers:
+          ads: { type: http, behavior: domain, url: "https://example.com/ads.yaml" }
+        sub-rules:
+          inner:
+            - DOMAIN,x.com,Missing
+        rules:
+          - RULE-SET,ads,REJECT
+          - RULE-SET,tracker,REJECT
+          - AND,((RULE-SET,cdn),(NETWORK,UDP)),DIRECT
+          - DOMAIN,a.com,Nowhere
+          - SUB-RULE,(NETWORK,tcp),inner
+          - MATCH,Proxy
+          - DOMAIN,late.com,DIRECT
+        "#,
+    )
+    .unwrap();
+
+    let mut trace = EnhanceTrace::new(&before);
+    trace.record("merge", Some("m1"), &after);
+    let steps = trace.into_steps();
+
+    let issues = lint_config(&after, &steps);
+    let kinds = issues
+        .iter()
+        .map(|issue| (issue.kind.as_str(), issue.path.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            ("duplicate-name", "proxies.a"),
+            ("missing-proxy", "proxy-groups.Proxy"),
+            ("missing-provider", "proxy-groups.Auto"),
+            ("empty-group", "proxy-groups.Empty"),
+            ("missing-rule-provider", "rules.1"),
+            ("missing-rule-provider", "rules.2"),
+            ("missing-target", "rules.3"),
+            ("unreachable-rule", "rules.6"),
+            ("missing-target", "sub-rules.inner.0"),
+        ]
+    );
+
+    // 删除节点 b 的是 merge，问题应归到该步骤
+    assert_eq!(issues[1].uid.as_deref(), Some("m1"));
+    assert_eq!(issues[1].level, LintLevel::Error);
+    assert_eq!(issues[7].level, LintLevel::Warning);
+    assert!(lint_config(&before, &[]).is_empty());
+}
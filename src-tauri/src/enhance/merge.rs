@@ -1,28 +1,119 @@
-use super::use_lowercase;
+use super::{use_keys, use_lowercase};
 use serde_yaml::{self, Mapping, Value};
+use std::borrow::Cow;
+
+/// Merge 文件中的指令
+enum Directive<'a> {
+    /// `prepend-<key>`：插入到列表开头
+    Prepend(&'a str),
+    /// `append-<key>`：追加到列表末尾
+    Append(&'a str),
+    /// `<key>!`：整体替换，不做深度合并
+    Replace(&'a str),
+    /// `delete-<key>: true`：删除该键
+    Delete(&'a str),
+}
+
+fn parse_directive<'a>(key: &'a str, value: &Value) -> Option<Directive<'a>> {
+    if let Some(name) = key.strip_prefix("prepend-") {
+        return value.is_sequence().then_some(Directive::Prepend(name));
+    }
+    if let Some(name) = key.strip_prefix("append-") {
+        return value.is_sequence().then_some(Directive::Append(name));
+    }
+    if let Some(name) = key.strip_prefix("delete-") {
+        return value.is_bool().then_some(Directive::Delete(name));
+    }
+    key.strip_suffix('!').map(Directive::Replace)
+}
+
+/// 把 `patch` 合并进 `target`，依次处理删除、覆盖与合并、列表插入
+fn merge_mapping(target: &mut Mapping, patch: &Mapping) {
+    let mut lists = vec![];
+
+    for (key, value) in patch {
+        if let Some(Directive::Delete(name)) = key.as_str().and_then(|k| parse_directive(k, value))
+        {
+            if value.as_bool() == Some(true) {
+                target.remove(name);
+            }
+        }
+    }
+
+    for (key, value) in patch {
+        match key.as_str().and_then(|k| parse_directive(k, value)) {
+            Some(Directive::Delete(_)) => {}
+            Some(Directive::Prepend(name)) => lists.push((name, value, true)),
+            Some(Directive::Append(name)) => lists.push((name, value, false)),
+            Some(Directive::Replace(name)) => {
+                let mut replaced = Value::Null;
+                deep_merge(&mut replaced, value);
+                target.insert(name.into(), replaced);
+            }
+            None => deep_merge(target.entry(key.clone()).or_insert(Value::Null), value),
+        }
+    }
+
+    for (name, value, prepend) in lists {
+        let mut items = value.as_sequence().cloned().unwrap_or_default();
+        match target.get_mut(name) {
+            Some(Value::Sequence(seq)) if prepend => {
+                items.append(seq);
+                *seq = items;
+            }
+            Some(Value::Sequence(seq)) => seq.append(&mut items),
+            _ => {
+                target.insert(name.into(), Value::Sequence(items));
+            }
+        }
+    }
+}
 
 fn deep_merge(a: &mut Value, b: &Value) {
     match (a, b) {
-        (&mut Value::Mapping(ref mut a), Value::Mapping(b)) => {
-            for (k, v) in b {
-                deep_merge(a.entry(k.clone()).or_insert(Value::Null), v);
-            }
+        (&mut Value::Mapping(ref mut a), Value::Mapping(b)) => merge_mapping(a, b),
+        // 目标不是 Mapping 时也要展开 b 中的指令
+        (a, Value::Mapping(b)) => {
+            let mut mapping = Mapping::new();
+            merge_mapping(&mut mapping, b);
+            *a = Value::Mapping(mapping);
         }
         (a, b) => *a = b.clone(),
     }
 }
 
-pub fn use_merge(merge: Mapping, config: Mapping) -> Mapping {
-    let mut config = Value::from(config);
-    let merge = use_lowercase(merge.clone());
-
-    deep_merge(&mut config, &Value::from(merge));
-
-    let config = config.as_mapping().unwrap().clone();
+pub fn use_merge(merge: &Mapping, mut config: Mapping) -> Mapping {
+    // 顶层键通常已经是小写，此时不需要复制
+    let is_lowercase = merge.keys().all(|key| {
+        key.as_str()
+            .is_some_and(|key| !key.bytes().any(|b| b.is_ascii_uppercase()))
+    });
+    let merge = match is_lowercase {
+        true => Cow::Borrowed(merge),
+        false => Cow::Owned(use_lowercase(merge.clone())),
+    };
 
+    merge_mapping(&mut config, &merge);
     config
 }
 
+/// Merge 文件涉及的顶层键，指令会还原为实际操作的键
+pub fn use_merge_keys(merge: &Mapping) -> Vec<String> {
+    use_keys(merge)
+        .into_iter()
+        .filter_map(|key| {
+            let value = merge.get(key.as_str()).unwrap_or(&Value::Null);
+            match parse_directive(&key, value) {
+                Some(Directive::Delete(_)) => None,
+                Some(Directive::Prepend(name))
+                | Some(Directive::Append(name))
+                | Some(Directive::Replace(name)) => Some(name.to_string()),
+                None => Some(key.clone()),
+            }
+        })
+        .collect()
+}
+
 #[test]
 fn test_merge() -> anyhow::Result<()> {
     let merge = r"
@@ -37,7 +128,7 @@ fn test_merge() -> anyhow::Result<()> {
       - 1111
     rules:
       - replace
-    proxy-groups: 
+    proxy-groups:
       - 123781923810
     tun:
       enable: true
@@ -54,7 +145,88 @@ fn test_merge() -> anyhow::Result<()> {
     let merge = serde_yaml::from_str::<Mapping>(merge)?;
     let config = serde_yaml::from_str::<Mapping>(config)?;
 
-    let _ = serde_yaml::to_string(&use_merge(merge, config))?;
+    let result = use_merge(&merge, config);
+
+    let expected = r"
+    rules:
+      - prepend
+      - 1123123
+      - replace
+      - append
+    script1: test
+    proxies:
+      - 9999
+      - 1111
+    proxy-groups:
+      - 123781923810
+    tun:
+      enable: true
+    dns:
+      enable: true
+  ";
+    assert_eq!(result, serde_yaml::from_str::<Mapping>(expected)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_nested_directives() -> anyhow::Result<()> {
+    let merge = r"
+    dns:
+      prepend-nameserver:
+        - 223.5.5.5
+      fallback!:
+        - 1.1.1.1
+      delete-fake-ip-filter: true
+    tun!:
+      enable: true
+    delete-hosts: true
+    sniffer:
+      append-skip-domain:
+        - example.com
+  ";
+
+    let config = r"
+    dns:
+      enable: true
+      nameserver:
+        - 8.8.8.8
+      fallback:
+        - 9.9.9.9
+      fake-ip-filter:
+        - '*.lan'
+    tun:
+      enable: false
+      stack: gvisor
+    hosts:
+      a.com: 127.0.0.1
+  ";
+
+    let merge = serde_yaml::from_str::<Mapping>(merge)?;
+    let config = serde_yaml::from_str::<Mapping>(config)?;
+
+    let expected = r"
+    dns:
+      enable: true
+      nameserver:
+        - 223.5.5.5
+        - 8.8.8.8
+      fallback:
+        - 1.1.1.1
+    tun:
+      enable: true
+    sniffer:
+      skip-domain:
+        - example.com
+  ";
+    assert_eq!(
+        use_merge(&merge, config),
+        serde_yaml::from_str::<Mapping>(expected)?
+    );
+
+    let mut keys = use_merge_keys(&merge);
+    keys.sort();
+    assert_eq!(keys, vec!["dns", "sniffer", "tun"]);
 
     Ok(())
 }
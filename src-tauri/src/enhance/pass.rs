@@ -0,0 +1,695 @@
+use super::{
+    chain::{ChainItem, ChainType},
+    field::{use_keys, use_sort},
+    merge::{use_merge, use_merge_keys},
+    profile_proxies,
+    script::{use_script, use_script_with_profiles, ProxiesLookup},
+    seq::use_seq,
+    trace::{EnhanceStep, EnhanceTrace},
+    tun::use_tun,
+    ResultLog,
+};
+use crate::{config::Config, utils::dirs};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_yaml::Mapping;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    path::Path,
+    time::SystemTime,
+};
+
+/// 文件的修改时间和长度，文件被编辑后至少有一项不同
+pub type FileVersion = (SystemTime, u64);
+
+pub fn file_version(path: &Path) -> Option<FileVersion> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// 增强流程的全部输入，在流程开始前一次性读取
+#[derive(Debug, Clone, Default)]
+pub struct EnhanceInput {
+    /// config.yaml 的内容
+    pub clash_config: Mapping,
+    pub clash_core: Option<String>,
+    pub enable_tun: bool,
+    pub enable_builtin: bool,
+    pub socks_enabled: bool,
+    pub http_enabled: bool,
+    pub redir_enabled: bool,
+    pub tproxy_enabled: bool,
+    pub enable_dns_settings: bool,
+    /// dns_config.yaml 的内容，未启用或读取失败时为空
+    pub dns_config: Option<Mapping>,
+    pub profile_name: String,
+    pub global_chain: Vec<ChainItem>,
+    pub profile_chain: Vec<ChainItem>,
+    /// 供脚本读取其他订阅的节点
+    pub proxies_lookup: Option<ProxiesLookup>,
+    /// 有脚本时记录各订阅文件的修改时间和长度，脚本可能读取其他订阅
+    pub profile_versions: Vec<(String, Option<FileVersion>)>,
+}
+
+impl EnhanceInput {
+    /// 读取当前的设置与订阅，返回输入和当前订阅的内容
+    pub fn load() -> (Self, Mapping) {
+        let clash_config = Config::clash().latest().0.clone();
+
+        let mut input = {
+            let verge = Config::verge();
+            let verge = verge.latest();
+            EnhanceInput {
+                clash_config,
+                clash_core: Some(verge.get_valid_clash_core()),
+                enable_tun: verge.enable_tun_mode.unwrap_or(false),
+                enable_builtin: verge.enable_builtin_enhanced.unwrap_or(true),
+                socks_enabled: verge.verge_socks_enabled.unwrap_or(false),
+                http_enabled: verge.verge_http_enabled.unwrap_or(false),
+                redir_enabled: verge.verge_redir_enabled.unwrap_or(false),
+                tproxy_enabled: verge.verge_tproxy_enabled.unwrap_or(false),
+                enable_dns_settings: verge.enable_dns_settings.unwrap_or(false),
+                proxies_lookup: Some(profile_proxies),
+                ..EnhanceInput::default()
+            }
+        };
+
+        if input.enable_dns_settings {
+            input.dns_config = dirs::app_home_dir()
+                .ok()
+                .map(|dir| dir.join("dns_config.yaml"))
+                .filter(|path| path.exists())
+                .and_then(|path| std::fs::read_to_string(path).ok())
+                .and_then(|yaml| serde_yaml::from_str::<Mapping>(&yaml).ok());
+        }
+
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let load_chain = |uids: Vec<String>| {
+            uids.iter()
+                .filter_map(|uid| {
+                    profiles
+                        .get_item(uid)
+                        .ok()
+                        .and_then(<Option<ChainItem>>::from)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // 全局扩展按列表顺序执行，跳过未启用和读取失败的项
+        input.global_chain = load_chain(
+            profiles
+                .get_global_chain()
+                .into_iter()
+                .filter(|entry| entry.enabled)
+                .map(|entry| entry.uid)
+                .collect(),
+        );
+        // 订阅引用的扩展，跳过读取失败的项
+        input.profile_chain = load_chain(profiles.current_chain());
+        input.profile_name = profiles
+            .get_item(&profiles.get_current().unwrap_or_default())
+            .ok()
+            .and_then(|item| item.name.clone())
+            .unwrap_or_default();
+
+        let has_script = input
+            .global_chain
+            .iter()
+            .chain(input.profile_chain.iter())
+            .any(|item| matches!(item.data, ChainType::Script(_)));
+        if has_script {
+            let dir = dirs::app_profiles_dir().ok();
+            input.profile_versions = profiles
+                .get_items()
+                .into_iter()
+                .flatten()
+                .filter(|item| item.is_profile())
+                .filter_map(|item| {
+                    let path = dir.as_ref()?.join(item.file.as_ref()?);
+                    Some((item.uid.clone()?, file_version(&path)))
+                })
+                .collect();
+        }
+
+        (input, profiles.current_mapping().unwrap_or_default())
+    }
+
+    /// 输入与订阅内容的指纹，相同时增强结果也相同
+    pub fn fingerprint(&self, config: &Mapping) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        config.hash(&mut hasher);
+        self.clash_config.hash(&mut hasher);
+        self.clash_core.hash(&mut hasher);
+        (
+            self.enable_tun,
+            self.enable_builtin,
+            self.socks_enabled,
+            self.http_enabled,
+            self.redir_enabled,
+            self.tproxy_enabled,
+            self.enable_dns_settings,
+        )
+            .hash(&mut hasher);
+        self.dns_config.hash(&mut hasher);
+        self.profile_name.hash(&mut hasher);
+        self.global_chain.hash(&mut hasher);
+        self.profile_chain.hash(&mut hasher);
+        self.profile_versions.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// 流程执行中的配置与收集到的结果
+pub struct EnhanceState {
+    pub config: Mapping,
+    /// 订阅中（包括merge和script生成的）出现过的keys
+    pub exists_keys: Vec<String>,
+    /// 以 uid 为键的脚本日志
+    pub result_map: HashMap<String, ResultLog>,
+    trace: EnhanceTrace,
+    recorded: usize,
+}
+
+impl EnhanceState {
+    pub fn new(config: Mapping) -> Self {
+        Self {
+            exists_keys: use_keys(&config),
+            result_map: HashMap::new(),
+            trace: EnhanceTrace::new(&config),
+            recorded: 0,
+            config,
+        }
+    }
+
+    /// 记录当前配置相对上一步的变化
+    pub fn record(&mut self, step: &str, uid: Option<&str>) {
+        self.trace.record(step, uid, &self.config);
+        self.recorded += 1;
+    }
+
+    /// 返回最终配置、去重后的keys、脚本日志和每一步的变化
+    pub fn finish(
+        self,
+    ) -> (
+        Mapping,
+        Vec<String>,
+        HashMap<String, ResultLog>,
+        Vec<EnhanceStep>,
+    ) {
+        let exists_keys: HashSet<String> = self.exists_keys.into_iter().collect();
+        (
+            self.config,
+            exists_keys.into_iter().collect(),
+            self.result_map,
+            self.trace.into_steps(),
+        )
+    }
+}
+
+/// 增强流程中的一步
+#[async_trait]
+pub trait EnhancePass: Send + Sync {
+    /// 唯一名称，同时作为 trace 中的步骤名
+    fn name(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    /// 该步骤读取的输入
+    fn inputs(&self) -> &'static [&'static str];
+
+    fn enabled(&self, _input: &EnhanceInput) -> bool {
+        true
+    }
+
+    /// 修改 `state.config`，没有调用 `state.record` 时以步骤名记录一次变化
+    async fn run(&self, input: &EnhanceInput, state: &mut EnhanceState) -> Result<()>;
+}
+
+/// 对外展示的步骤信息
+#[derive(Debug, Clone, Serialize)]
+pub struct PassInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub inputs: &'static [&'static str],
+    pub enabled: bool,
+}
+
+/// 默认的增强流程，按执行顺序排列
+pub fn default_pipeline() -> Vec<Box<dyn EnhancePass>> {
+    vec![
+        Box::new(GlobalChainPass),
+        Box::new(ProfileChainPass),
+        Box::new(ClashConfigPass),
+        Box::new(BuiltinPass),
+        Box::new(TunPass),
+        Box::new(SortPass),
+        Box::new(DnsPass),
+    ]
+}
+
+pub fn describe_pipeline(pipeline: &[Box<dyn EnhancePass>], input: &EnhanceInput) -> Vec<PassInfo> {
+    pipeline
+        .iter()
+        .map(|pass| PassInfo {
+            name: pass.name(),
+            description: pass.description(),
+            inputs: pass.inputs(),
+            enabled: pass.enabled(input),
+        })
+        .collect()
+}
+
+/// 依次执行启用的步骤，单个步骤出错时保留之前的配置并记录到该步骤的日志
+pub async fn run_pipeline(
+    pipeline: &[Box<dyn EnhancePass>],
+    input: &EnhanceInput,
+    config: Mapping,
+) -> EnhanceState {
+    let mut state = EnhanceState::new(config);
+
+    for pass in pipeline.iter().filter(|pass| pass.enabled(input)) {
+        let recorded = state.recorded;
+        if let Err(err) = pass.run(input, &mut state).await {
+            log::error!(target: "app", "enhance pass `{}` error: {err}", pass.name());
+            state
+                .result_map
+                .entry(pass.name().into())
+                .or_default()
+                .push(("exception".into(), err.to_string()));
+        }
+        if state.recorded == recorded {
+            state.record(pass.name(), None);
+        }
+    }
+
+    state
+}
+
+/// 执行一个扩展并以 `step` 记录变化
+fn apply_chain_item(state: &mut EnhanceState, input: &EnhanceInput, item: &ChainItem, step: &str) {
+    let config = std::mem::take(&mut state.config);
+    state.config = match &item.data {
+        ChainType::Merge(merge) => {
+            state.exists_keys.extend(use_merge_keys(merge));
+            use_merge(merge, config)
+        }
+        ChainType::Script(script) => {
+            let name = input.profile_name.clone();
+            let (config, logs) = match input.proxies_lookup {
+                Some(lookup) => use_script_with_profiles(script, config, name, lookup),
+                None => use_script(script, config, name),
+            };
+            state.exists_keys.extend(use_keys(&config));
+            state.result_map.insert(item.uid.clone(), logs);
+            config
+        }
+        ChainType::Rules(rules) => use_seq(rules, config, "rules"),
+        ChainType::Proxies(proxies) => use_seq(proxies, config, "proxies"),
+        ChainType::Groups(groups) => use_seq(groups, config, "proxy-groups"),
+    };
+    state.record(step, Some(&item.uid));
+}
+
+/// 全局扩展
+pub struct GlobalChainPass;
+
+#[async_trait]
+impl EnhancePass for GlobalChainPass {
+    fn name(&self) -> &'static str {
+        "global-chain"
+    }
+
+    fn description(&self) -> &'static str {
+        "apply enabled global chain items in order"
+    }
+
+    fn inputs(&self) -> &'static [&'static str] {
+        &["profiles.global_chain"]
+    }
+
+    fn enabled(&self, input: &EnhanceInput) -> bool {
+        !input.global_chain.is_empty()
+    }
+
+    async fn run(&self, input: &EnhanceInput, state: &mut EnhanceState) -> Result<()> {
+        for item in input.global_chain.iter() {
+            let step = format!("global-{}", item.data.kind());
+            apply_chain_item(state, input, item, &step);
+        }
+        Ok(())
+    }
+}
+
+/// 订阅引用的扩展
+pub struct ProfileChainPass;
+
+#[async_trait]
+impl EnhancePass for ProfileChainPass {
+    fn name(&self) -> &'static str {
+        "profile-chain"
+    }
+
+    fn description(&self) -> &'static str {
+        "apply the chain items of the current profile in order"
+    }
+
+    fn inputs(&self) -> &'static [&'static str] {
+        &["profiles.current", "profile.chain"]
+    }
+
+    fn enabled(&self, input: &EnhanceInput) -> bool {
+        !input.profile_chain.is_empty()
+    }
+
+    async fn run(&self, input: &EnhanceInput, state: &mut EnhanceState) -> Result<()> {
+        for item in input.profile_chain.iter() {
+            apply_chain_item(state, input, item, item.data.kind());
+        }
+        Ok(())
+    }
+}
+
+/// 合并 config.yaml，去掉未启用的端口，tun 只补充缺少的字段
+pub struct ClashConfigPass;
+
+#[async_trait]
+impl EnhancePass for ClashConfigPass {
+    fn name(&self) -> &'static str {
+        "clash-config"
+    }
+
+    fn description(&self) -> &'static str {
+        "merge config.yaml and drop the ports that are turned off"
+    }
+
+    fn inputs(&self) -> &'static [&'static str] {
+        &[
+            "config.yaml",
+            "verge_socks_enabled",
+            "verge_http_enabled",
+            "verge_redir_enabled",
+            "verge_tproxy_enabled",
+        ]
+    }
+
+    async fn run(&self, input: &EnhanceInput, state: &mut EnhanceState) -> Result<()> {
+        let config = &mut state.config;
+        for (key, value) in input.clash_config.iter() {
+            if key.as_str() == Some("tun") {
+                let mut tun = config.get_mut("tun").map_or(Mapping::new(), |val| {
+                    val.as_mapping().cloned().unwrap_or(Mapping::new())
+                });
+                let patch_tun = value.as_mapping().cloned().unwrap_or(Mapping::new());
+                for (key, value) in patch_tun.into_iter() {
+                    if !tun.contains_key(&key) {
+                        tun.insert(key, value);
+                    }
+                }
+                config.insert("tun".into(), tun.into());
+            } else {
+                if key.as_str() == Some("socks-port") && !input.socks_enabled {
+                    config.remove("socks-port");
+                    continue;
+                }
+                if key.as_str() == Some("port") && !input.http_enabled {
+                    config.remove("port");
+                    continue;
+                }
+                #[cfg(not(target_os = "windows"))]
+                {
+                    if key.as_str() == Some("redir-port") && !input.redir_enabled {
+                        config.remove("redir-port");
+                        continue;
+                    }
+                }
+                #[cfg(target_os = "linux")]
+                {
+                    if key.as_str() == Some("tproxy-port") && !input.tproxy_enabled {
+                        config.remove("tproxy-port");
+                        continue;
+                    }
+                }
+                config.insert(key.clone(), value.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 内建脚本，按内核类型筛选
+pub struct BuiltinPass;
+
+#[async_trait]
+impl EnhancePass for BuiltinPass {
+    fn name(&self) -> &'static str {
+        "builtin"
+    }
+
+    fn description(&self) -> &'static str {
+        "run the builtin scripts supported by the current core"
+    }
+
+    fn inputs(&self) -> &'static [&'static str] {
+        &["enable_builtin_enhanced", "clash_core"]
+    }
+
+    fn enabled(&self, input: &EnhanceInput) -> bool {
+        input.enable_builtin
+    }
+
+    async fn run(&self, input: &EnhanceInput, state: &mut EnhanceState) -> Result<()> {
+        let items = ChainItem::builtin()
+            .into_iter()
+            .filter(|(s, _)| s.is_support(input.clash_core.as_ref()))
+            .map(|(_, c)| c);
+
+        for item in items {
+            log::debug!(target: "app", "run builtin script {0}", item.uid);
+            if let ChainType::Script(script) = item.data {
+                let config = std::mem::take(&mut state.config);
+                let (config, logs) = use_script(&script, config, "".to_string());
+                state.config = config;
+                for (_, err) in logs
+                    .iter()
+                    .filter(|(level, _)| level == "exception" || level == "limit")
+                {
+                    log::error!(target: "app", "builtin script error `{err}`");
+                }
+                state.record(self.name(), Some(&item.uid));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 写入 tun 开关，启用时补全 DNS 设置
+pub struct TunPass;
+
+#[async_trait]
+impl EnhancePass for TunPass {
+    fn name(&self) -> &'static str {
+        "tun"
+    }
+
+    fn description(&self) -> &'static str {
+        "set tun.enable and the dns settings tun mode needs"
+    }
+
+    fn inputs(&self) -> &'static [&'static str] {
+        &["enable_tun_mode"]
+    }
+
+    async fn run(&self, input: &EnhanceInput, state: &mut EnhanceState) -> Result<()> {
+        let config = std::mem::take(&mut state.config);
+        state.config = use_tun(config, input.enable_tun).await;
+        Ok(())
+    }
+}
+
+/// 按固定顺序排列顶层字段
+pub struct SortPass;
+
+#[async_trait]
+impl EnhancePass for SortPass {
+    fn name(&self) -> &'static str {
+        "sort"
+    }
+
+    fn description(&self) -> &'static str {
+        "sort the top level keys"
+    }
+
+    fn inputs(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    async fn run(&self, _input: &EnhanceInput, state: &mut EnhanceState) -> Result<()> {
+        let config = std::mem::take(&mut state.config);
+        state.config = use_sort(config);
+        Ok(())
+    }
+}
+
+/// 应用独立的 DNS 配置，包含 `dns` 时只取其中的 `dns` 与 `hosts`
+pub struct DnsPass;
+
+#[async_trait]
+impl EnhancePass for DnsPass {
+    fn name(&self) -> &'static str {
+        "dns"
+    }
+
+    fn description(&self) -> &'static str {
+        "overlay dns_config.yaml"
+    }
+
+    fn inputs(&self) -> &'static [&'static str] {
+        &["enable_dns_settings", "dns_config.yaml"]
+    }
+
+    fn enabled(&self, input: &EnhanceInput) -> bool {
+        input.enable_dns_settings
+    }
+
+    async fn run(&self, input: &EnhanceInput, state: &mut EnhanceState) -> Result<()> {
+        let Some(dns_config) = input.dns_config.as_ref() else {
+            return Ok(());
+        };
+        let config = &mut state.config;
+
+        // 处理hosts配置
+        if let Some(hosts_value) = dns_config.get("hosts") {
+            if hosts_value.is_mapping() {
+                config.insert("hosts".into(), hosts_value.clone());
+                log::info!(target: "app", "apply hosts configuration");
+            }
+        }
+
+        if let Some(dns_value) = dns_config.get("dns") {
+            if let Some(dns_mapping) = dns_value.as_mapping() {
+                config.insert("dns".into(), dns_mapping.clone().into());
+                log::info!(target: "app", "apply dns_config.yaml (dns section)");
+            }
+        } else {
+            config.insert("dns".into(), dns_config.clone().into());
+            log::info!(target: "app", "apply dns_config.yaml");
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_clash_config_pass() {
+    let input = EnhanceInput {
+        clash_config: serde_yaml::from_str(
+            r"
+            mixed-port: 7897
+            socks-port: 7898
+            port: 7899
+            tun:
+              stack: gvisor
+              enable: false
+            ",
+        )
+        .unwrap(),
+        http_enabled: true,
+        ..EnhanceInput::default()
+    };
+    let config = serde_yaml::from_str(
+        r"
+        socks-port: 1080
+        tun:
+          stack: system
+        ",
+    )
+    .unwrap();
+
+    let state = run_pipeline(&[Box::new(ClashConfigPass)], &input, config).await;
+    let expected = serde_yaml::from_str::<Mapping>(
+        r"
+        mixed-port: 7897
+        port: 7899
+        tun:
+          stack: system
+          enable: false
+        ",
+    )
+    .unwrap();
+    assert_eq!(state.config, expected);
+
+    let (_, _, _, steps) = state.finish();
+    assert_eq!(steps.len(), 1);
+    assert_eq!(steps[0].step, "clash-config");
+    assert_eq!(steps[0].diff.removed, vec!["socks-port"]);
+}
+
+#[test]
+fn test_fingerprint() {
+    let config = serde_yaml::from_str::<Mapping>("rules: ['MATCH,DIRECT']").unwrap();
+    let input = EnhanceInput {
+        enable_tun: true,
+        ..EnhanceInput::default()
+    };
+    let fingerprint = input.fingerprint(&config);
+    assert_eq!(fingerprint, input.clone().fingerprint(&config));
+
+    let mut changed = input.clone();
+    changed.enable_tun = false;
+    assert_ne!(fingerprint, changed.fingerprint(&config));
+
+    let mut changed = input.clone();
+    changed.profile_chain.push(ChainItem {
+        uid: "m1".into(),
+        data: ChainType::Merge(Mapping::new()),
+    });
+    assert_ne!(fingerprint, changed.fingerprint(&config));
+
+    assert_ne!(fingerprint, input.fingerprint(&Mapping::new()));
+}
+
+#[test]
+fn test_fingerprint_profile_file_edited() {
+    let dir = std::env::temp_dir().join(format!("enhance-pass-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("profile.yaml");
+    std::fs::write(&path, "proxies: []").unwrap();
+
+    // 脚本会读取其他订阅，订阅文件被编辑后不能复用上次的结果
+    let script = ChainItem {
+        uid: "s1".into(),
+        data: ChainType::Script("function main(config) { return config }".into()),
+    };
+    let mut input = EnhanceInput {
+        profile_chain: vec![script],
+        profile_versions: vec![("p1".into(), file_version(&path))],
+        ..EnhanceInput::default()
+    };
+    let config = Mapping::new();
+    let fingerprint = input.fingerprint(&config);
+
+    std::fs::write(&path, "proxies: [{name: HK 01, type: direct}]").unwrap();
+    input.profile_versions = vec![("p1".into(), file_version(&path))];
+    assert_ne!(fingerprint, input.fingerprint(&config));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_pipeline_describe() {
+    let input = EnhanceInput {
+        enable_dns_settings: true,
+        dns_config: serde_yaml::from_str("hosts: { a.com: 1.1.1.1 }\ndns: { enable: true }").ok(),
+        ..EnhanceInput::default()
+    };
+    let pipeline = default_pipeline();
+    let info = describe_pipeline(&pipeline, &input);
+    let enabled: Vec<_> = info.iter().filter(|p| p.enabled).map(|p| p.name).collect();
+    assert_eq!(enabled, vec!["clash-config", "tun", "sort", "dns"]);
+
+    let state = run_pipeline(&[Box::new(DnsPass)], &input, Mapping::new()).await;
+    assert_eq!(state.config["hosts"]["a.com"].as_str(), Some("1.1.1.1"));
+    assert_eq!(state.config["dns"]["enable"].as_bool(), Some(true));
+}
@@ -0,0 +1,575 @@
+use super::lint::{logic_operands, split_rule, RuleParts};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::{collections::HashMap, net::IpAddr, path::Path, rc::Rc};
+
+/// sub-rules 互相引用时的最大深度
+const MAX_SUB_RULE_DEPTH: usize = 8;
+
+/// 待判断的连接，未提供的字段视为该连接没有这项信息
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RouteQuery {
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub ip: Option<IpAddr>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// tcp 或 udp，默认 tcp
+    #[serde(default)]
+    pub network: Option<String>,
+    #[serde(default)]
+    pub process: Option<String>,
+}
+
+/// 离线无法判断的规则，例如 GEOIP 或需要 DNS 解析的 IP 规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UndecidableRule {
+    pub path: String,
+    pub rule: String,
+    pub reason: String,
+}
+
+/// 命中的规则，没有规则命中时内核使用 DIRECT
+///
+/// undecidable 记录命中之前无法判断的规则，它们被当作未命中处理
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RouteResult {
+    pub path: Option<String>,
+    pub rule: Option<String>,
+    pub target: String,
+    pub undecidable: Vec<UndecidableRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Verdict {
+    Match,
+    Miss,
+    Undecidable(String),
+}
+
+impl Verdict {
+    fn from_bool(matched: bool) -> Self {
+        match matched {
+            true => Verdict::Match,
+            false => Verdict::Miss,
+        }
+    }
+}
+
+/// 缓存的 rule-provider 内容
+enum RuleSet {
+    Domain(Vec<String>),
+    IpCidr(Vec<String>),
+    Classical(Vec<String>),
+}
+
+/// 按顺序遍历规则，返回第一条命中的规则
+///
+/// rule-provider 从 home 目录下缓存的文件读取
+pub fn match_rules(config: &Mapping, query: &RouteQuery, home: &Path) -> RouteResult {
+    let mut matcher = Matcher {
+        config,
+        query,
+        home,
+        rule_sets: HashMap::new(),
+        undecidable: vec![],
+    };
+    let matched = matcher.walk("rules", config.get("rules"), 0);
+    let undecidable = matcher.undecidable;
+    match matched {
+        Some((path, rule, target)) => RouteResult {
+            path: Some(path),
+            rule: Some(rule),
+            target,
+            undecidable,
+        },
+        None => RouteResult {
+            path: None,
+            rule: None,
+            target: "DIRECT".into(),
+            undecidable,
+        },
+    }
+}
+
+struct Matcher<'a> {
+    config: &'a Mapping,
+    query: &'a RouteQuery,
+    home: &'a Path,
+    rule_sets: HashMap<String, Rc<Result<RuleSet, String>>>,
+    undecidable: Vec<UndecidableRule>,
+}
+
+impl Matcher<'_> {
+    /// 返回命中规则的路径、规则和目标
+    fn walk(
+        &mut self,
+        path: &str,
+        rules: Option<&Value>,
+        depth: usize,
+    ) -> Option<(String, String, String)> {
+        let rules = rules.and_then(Value::as_sequence)?;
+        for (index, rule) in rules.iter().filter_map(Value::as_str).enumerate() {
+            let rule_path = format!("{path}.{index}");
+            let verdict = match split_rule(rule, true) {
+                Some(parts) => self.eval(&parts),
+                None => Verdict::Undecidable("invalid rule".into()),
+            };
+
+            match verdict {
+                Verdict::Miss => {}
+                Verdict::Undecidable(reason) => self.undecidable.push(UndecidableRule {
+                    path: rule_path,
+                    rule: rule.into(),
+                    reason,
+                }),
+                Verdict::Match => {
+                    let parts = split_rule(rule, true)?;
+                    let target = parts.target.unwrap_or_default();
+                    if parts.kind != "SUB-RULE" {
+                        return Some((rule_path, rule.into(), target.into()));
+                    }
+                    if depth >= MAX_SUB_RULE_DEPTH {
+                        self.undecidable.push(UndecidableRule {
+                            path: rule_path,
+                            rule: rule.into(),
+                            reason: "sub-rules are nested too deeply".into(),
+                        });
+                        continue;
+                    }
+                    // 子规则都不命中时继续匹配后面的规则
+                    let sub_rules = self.config.get("sub-rules").and_then(|map| map.get(target));
+                    let sub_path = format!("sub-rules.{target}");
+                    if let Some(found) = self.walk(&sub_path, sub_rules, depth + 1) {
+                        return Some(found);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn eval(&mut self, parts: &RuleParts) -> Verdict {
+        let query = self.query;
+        let domain = query.domain.as_deref().map(str::to_lowercase);
+        let payload = parts.payload;
+        let no_resolve = parts.params.contains(&"no-resolve");
+
+        match parts.kind.as_str() {
+            "MATCH" => Verdict::Match,
+            "DOMAIN" => Verdict::from_bool(domain.is_some_and(|d| d == payload.to_lowercase())),
+            "DOMAIN-SUFFIX" => {
+                Verdict::from_bool(domain.is_some_and(|d| domain_suffix(&d, payload)))
+            }
+            "DOMAIN-KEYWORD" => {
+                Verdict::from_bool(domain.is_some_and(|d| d.contains(&payload.to_lowercase())))
+            }
+            "DOMAIN-REGEX" => match (domain, Regex::new(payload)) {
+                (_, Err(err)) => Verdict::Undecidable(format!("invalid regex: {err}")),
+                (Some(d), Ok(regex)) => Verdict::from_bool(regex.is_match(&d)),
+                (None, _) => Verdict::Miss,
+            },
+            "IP-CIDR" | "IP-CIDR6" => match parse_cidr(payload) {
+                Some(cidr) => ip_verdict(query, no_resolve, |ip| cidr_contains(cidr, ip)),
+                None => Verdict::Undecidable(format!("invalid cidr `{payload}`")),
+            },
+            "DST-PORT" => match query.port {
+                Some(port) => match port_matches(payload, port) {
+                    Some(matched) => Verdict::from_bool(matched),
+                    None => Verdict::Undecidable(format!("invalid port `{payload}`")),
+                },
+                None => Verdict::Miss,
+            },
+            "NETWORK" => {
+                let network = query.network.as_deref().unwrap_or("tcp");
+                Verdict::from_bool(network.eq_ignore_ascii_case(payload))
+            }
+            "PROCESS-NAME" => Verdict::from_bool(
+                query
+                    .process
+                    .as_deref()
+                    .is_some_and(|process| process.eq_ignore_ascii_case(payload)),
+            ),
+            "AND" | "OR" | "NOT" | "SUB-RULE" => {
+                let operands = logic_operands(payload);
+                if operands.is_empty() {
+                    return Verdict::Undecidable("logic rule has no operands".into());
+                }
+                let verdicts = operands
+                    .into_iter()
+                    .map(|operand| match split_rule(operand, false) {
+                        Some(inner) => self.eval(&inner),
+                        None => Verdict::Undecidable(format!("invalid operand `{operand}`")),
+                    })
+                    .collect::<Vec<_>>();
+                match parts.kind.as_str() {
+                    "OR" => any(verdicts),
+                    "NOT" => match all(verdicts) {
+                        Verdict::Match => Verdict::Miss,
+                        Verdict::Miss => Verdict::Match,
+                        undecidable => undecidable,
+                    },
+                    _ => all(verdicts),
+                }
+            }
+            "RULE-SET" => self.eval_rule_set(payload, no_resolve),
+            kind => Verdict::Undecidable(format!("{kind} is not supported offline")),
+        }
+    }
+
+    fn eval_rule_set(&mut self, name: &str, no_resolve: bool) -> Verdict {
+        let rule_set = self
+            .rule_sets
+            .entry(name.into())
+            .or_insert_with(|| Rc::new(load_rule_set(self.config, self.home, name)))
+            .clone();
+        let rule_set = match rule_set.as_ref() {
+            Ok(rule_set) => rule_set,
+            Err(reason) => return Verdict::Undecidable(reason.clone()),
+        };
+
+        let query = self.query;
+        match rule_set {
+            RuleSet::Domain(entries) => {
+                let domain = query.domain.as_deref().map(str::to_lowercase);
+                Verdict::from_bool(
+                    domain
+                        .is_some_and(|d| entries.iter().any(|entry| domain_set_matches(&d, entry))),
+                )
+            }
+            RuleSet::IpCidr(entries) => {
+                let cidrs = entries
+                    .iter()
+                    .filter_map(|entry| parse_cidr(entry))
+                    .collect::<Vec<_>>();
+                ip_verdict(query, no_resolve, |ip| {
+                    cidrs.iter().any(|cidr| cidr_contains(*cidr, ip))
+                })
+            }
+            RuleSet::Classical(lines) => any(lines
+                .iter()
+                .map(|line| match split_rule(line, false) {
+                    Some(parts) if parts.kind == "RULE-SET" => {
+                        Verdict::Undecidable("nested RULE-SET".into())
+                    }
+                    Some(parts) => self.eval(&parts),
+                    None => Verdict::Undecidable(format!("invalid rule `{line}`")),
+                })
+                .collect()),
+        }
+    }
+}
+
+/// 任一命中即命中，否则只要有无法判断的就无法判断
+fn any(verdicts: Vec<Verdict>) -> Verdict {
+    let mut result = Verdict::Miss;
+    for verdict in verdicts {
+        match verdict {
+            Verdict::Match => return Verdict::Match,
+            Verdict::Undecidable(_) if result == Verdict::Miss => result = verdict,
+            _ => {}
+        }
+    }
+    result
+}
+
+/// 任一未命中即未命中，否则只要有无法判断的就无法判断
+fn all(verdicts: Vec<Verdict>) -> Verdict {
+    let mut result = Verdict::Match;
+    for verdict in verdicts {
+        match verdict {
+            Verdict::Miss => return Verdict::Miss,
+            Verdict::Undecidable(_) if result == Verdict::Match => result = verdict,
+            _ => {}
+        }
+    }
+    result
+}
+
+/// 只有域名时内核需要先解析，离线无法判断；no-resolve 时直接不命中
+fn ip_verdict(query: &RouteQuery, no_resolve: bool, contains: impl Fn(IpAddr) -> bool) -> Verdict {
+    match (query.ip, &query.domain) {
+        (Some(ip), _) => Verdict::from_bool(contains(ip)),
+        (None, Some(_)) if !no_resolve => {
+            Verdict::Undecidable("requires resolving the domain".into())
+        }
+        _ => Verdict::Miss,
+    }
+}
+
+/// 读取 rule-provider 缓存的文件，只支持 yaml 和 text 格式
+fn load_rule_set(config: &Mapping, home: &Path, name: &str) -> Result<RuleSet, String> {
+    let provider = config
+        .get("rule-providers")
+        .and_then(|providers| providers.get(name))
+        .ok_or(format!("rule provider `{name}` is not defined"))?;
+    let field = |key: &str| provider.get(key).and_then(Value::as_str);
+
+    let format = field("format").unwrap_or("yaml");
+    if format == "mrs" {
+        return Err(format!("rule provider `{name}` uses the binary mrs format"));
+    }
+    let path = field("path").ok_or(format!("rule provider `{name}` has no cached path"))?;
+    let content = std::fs::read_to_string(home.join(path))
+        .map_err(|_| format!("rule provider `{name}` is not cached at `{path}`"))?;
+
+    let entries = match format {
+        "text" => content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+        _ => serde_yaml::from_str::<Mapping>(&content)
+            .ok()
+            .and_then(|map| map.get("payload")?.as_sequence().cloned())
+            .ok_or(format!("rule provider `{name}` has no payload"))?
+            .into_iter()
+            .filter_map(|item| item.as_str().map(|s| s.trim().to_string()))
+            .collect(),
+    };
+
+    match field("behavior").unwrap_or("classical") {
+        "domain" => Ok(RuleSet::Domain(entries)),
+        "ipcidr" => Ok(RuleSet::IpCidr(entries)),
+        _ => Ok(RuleSet::Classical(entries)),
+    }
+}
+
+fn domain_suffix(domain: &str, suffix: &str) -> bool {
+    let suffix = suffix.trim_start_matches('.').to_lowercase();
+    domain == suffix || domain.ends_with(&format!(".{suffix}"))
+}
+
+/// domain 类型 rule-provider 的通配写法：`+.` 含自身及子域名，`.` 仅子域名，`*.` 仅一级子域名
+fn domain_set_matches(domain: &str, entry: &str) -> bool {
+    let entry = entry.to_lowercase();
+    if let Some(base) = entry.strip_prefix("+.") {
+        domain_suffix(domain, base)
+    } else if let Some(base) = entry.strip_prefix('.') {
+        domain.ends_with(&format!(".{base}"))
+    } else if let Some(base) = entry.strip_prefix("*.") {
+        domain
+            .strip_suffix(&format!(".{base}"))
+            .is_some_and(|label| !label.is_empty() && !label.contains('.'))
+    } else {
+        domain == entry
+    }
+}
+
+fn parse_cidr(cidr: &str) -> Option<(IpAddr, u8)> {
+    let (ip, prefix) = match cidr.split_once('/') {
+        Some((ip, prefix)) => (ip.parse::<IpAddr>().ok()?, prefix.parse::<u8>().ok()?),
+        None => {
+            let ip = cidr.parse::<IpAddr>().ok()?;
+            (ip, if ip.is_ipv4() { 32 } else { 128 })
+        }
+    };
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    (prefix <= max).then_some((ip, prefix))
+}
+
+fn cidr_contains((net, prefix): (IpAddr, u8), ip: IpAddr) -> bool {
+    match (net, ip) {
+        (IpAddr::V4(net), IpAddr::V4(ip)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(net) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(net), IpAddr::V6(ip)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(net) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+/// 端口写法：`443`、`1000-2000`，多个用 `/` 分隔
+fn port_matches(spec: &str, port: u16) -> Option<bool> {
+    let mut matched = false;
+    for part in spec.split('/').map(str::trim) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim().parse::<u16>().ok()?, end.trim().parse().ok()?),
+            None => {
+                let port = part.parse::<u16>().ok()?;
+                (port, port)
+            }
+        };
+        matched |= (start..=end).contains(&port);
+    }
+    Some(matched)
+}
+
+#[test]
+fn test_match_rules() {
+    let config = serde_yaml::from_str::<Mapping>(
+        r#"
+        rules:
+          - DOMAIN,exact.com,A
+          - DOMAIN-SUFFIX,google.com,B
+          - DOMAIN-KEYWORD,tracker,REJECT
+          - DOMAIN-REGEX,^ads\d+\.,REJECT
+          - IP-CIDR,10.0.0.0/8,DIRECT,no-resolve
+          - IP-CIDR6,2001:db8::/32,DIRECT
+          - AND,((NETWORK,UDP),(DST-PORT,443)),REJECT
+          - OR,((PROCESS-NAME,curl),(DST-PORT,8000-8080/9000)),C
+          - NOT,((DOMAIN-SUFFIX,com)),D
+          - GEOIP,CN,DIRECT
+          - MATCH,Final
+        "#,
+    )
+    .unwrap();
+    let home = Path::new("/nonexistent");
+    let route = |query: RouteQuery| match_rules(&config, &query, home);
+
+    let query = |domain: &str| RouteQuery {
+        domain: Some(domain.into()),
+        ..RouteQuery::default()
+    };
+    assert_eq!(route(query("Exact.com")).target, "A");
+    assert_eq!(route(query("mail.google.com")).target, "B");
+    assert_eq!(route(query("google.com")).target, "B");
+    assert_eq!(
+        route(query("xgoogle.com")).path.as_deref(),
+        Some("rules.10")
+    );
+    assert_eq!(route(query("a.tracker.net")).target, "REJECT");
+    assert_eq!(route(query("ads12.example.org")).target, "REJECT");
+
+    // 只有域名时 IP 规则需要解析，no-resolve 的规则直接跳过
+    let result = route(query("example.org"));
+    assert_eq!(result.target, "D");
+    assert_eq!(result.path.as_deref(), Some("rules.8"));
+    assert_eq!(
+        result
+            .undecidable
+            .iter()
+            .map(|rule| rule.path.as_str())
+            .collect::<Vec<_>>(),
+        vec!["rules.5"]
+    );
+
+    let result = route(RouteQuery {
+        ip: Some("10.1.2.3".parse().unwrap()),
+        ..RouteQuery::default()
+    });
+    assert_eq!(result.target, "DIRECT");
+    assert_eq!(result.path.as_deref(), Some("rules.4"));
+
+    let result = route(RouteQuery {
+        domain: Some("example.com".into()),
+        network: Some("udp".into()),
+        port: Some(443),
+        ..RouteQuery::default()
+    });
+    assert_eq!(result.target, "REJECT");
+
+    let result = route(RouteQuery {
+        domain: Some("example.com".into()),
+        port: Some(8080),
+        ..RouteQuery::default()
+    });
+    assert_eq!(result.target, "C");
+
+    let result = route(RouteQuery {
+        ip: Some("8.8.8.8".parse().unwrap()),
+        ..RouteQuery::default()
+    });
+    assert_eq!(result.target, "D");
+
+    let result = route(RouteQuery {
+        domain: Some("example.com".into()),
+        process: Some("CURL".into()),
+        ..RouteQuery::default()
+    });
+    assert_eq!(result.target, "C");
+
+    let result = route(query("example.com"));
+    assert_eq!(result.target, "Final");
+    assert_eq!(
+        result.undecidable.last().map(|rule| rule.path.as_str()),
+        Some("rules.9")
+    );
+}
+
+#[test]
+fn test_match_rule_sets() {
+    let home = std::env::temp_dir().join(format!("route-test-{}", std::process::id()));
+    std::fs::create_dir_all(home.join("ruleset")).unwrap();
+    std::fs::write(
+        home.join("ruleset/ads.yaml"),
+        "payload:\n  - '+.ads.com'\n  - '.sub.net'\n  - '*.one.org'\n",
+    )
+    .unwrap();
+    std::fs::write(home.join("ruleset/lan.txt"), "# lan\n192.168.0.0/16\n").unwrap();
+    std::fs::write(
+        home.join("ruleset/mixed.yaml"),
+        "payload:\n  - DOMAIN-SUFFIX,mixed.io\n  - GEOSITE,cn\n",
+    )
+    .unwrap();
+
+    let config = serde_yaml::from_str::<Mapping>(
+        r#"
+        rule-providers:
+          ads: { type: file, behavior: domain, path: ./ruleset/ads.yaml }
+          lan: { type: file, behavior: ipcidr, format: text, path: ./ruleset/lan.txt }
+          mixed: { type: file, behavior: classical, path: ./ruleset/mixed.yaml }
+          remote: { type: http, behavior: domain, url: "https://example.com/r.yaml" }
+        sub-rules:
+          inner:
+            - RULE-SET,ads,REJECT
+        rules:
+          - SUB-RULE,(DST-PORT,443),inner
+          - RULE-SET,lan,DIRECT,no-resolve
+          - RULE-SET,mixed,Proxy
+          - RULE-SET,remote,REJECT
+          - MATCH,Final
+        "#,
+    )
+    .unwrap();
+    let route = |domain: &str, port: u16| {
+        let query = RouteQuery {
+            domain: Some(domain.into()),
+            port: Some(port),
+            ..RouteQuery::default()
+        };
+        match_rules(&config, &query, &home)
+    };
+
+    let result = route("x.ads.com", 443);
+    assert_eq!(result.target, "REJECT");
+    assert_eq!(result.path.as_deref(), Some("sub-rules.inner.0"));
+    assert_eq!(route("ads.com", 443).target, "REJECT");
+    assert_eq!(route("a.sub.net", 443).target, "REJECT");
+    assert_eq!(
+        route("a.b.one.org", 443).rule.as_deref(),
+        Some("MATCH,Final")
+    );
+    // 子规则不命中时继续匹配后面的规则
+    assert_eq!(route("ads.com", 80).rule.as_deref(), Some("MATCH,Final"));
+    assert_eq!(route("cdn.mixed.io", 80).target, "Proxy");
+
+    let result = route("other.com", 80);
+    assert_eq!(result.target, "Final");
+    assert_eq!(
+        result
+            .undecidable
+            .iter()
+            .map(|rule| rule.path.as_str())
+            .collect::<Vec<_>>(),
+        vec!["rules.2", "rules.3"]
+    );
+
+    let result = match_rules(
+        &config,
+        &RouteQuery {
+            ip: Some("192.168.1.1".parse().unwrap()),
+            ..RouteQuery::default()
+        },
+        &home,
+    );
+    assert_eq!(result.target, "DIRECT");
+
+    let _ = std::fs::remove_dir_all(&home);
+}
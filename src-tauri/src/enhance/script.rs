@@ -1,19 +1,415 @@
 use super::use_lowercase;
+use crate::utils::convert::decode_base64;
 use anyhow::{Error, Result};
-use serde_yaml::Mapping;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Sequence};
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, BufRead, Write},
+    rc::Rc,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
 
-pub fn use_script(
+/// 脚本线程的栈大小，Boa 的解释器递归较深
+const SCRIPT_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+/// 以该参数启动自身时作为脚本子进程运行
+pub const SCRIPT_WORKER_ARG: &str = "--verge-script-worker";
+
+/// 按名称或 uid 查找其他订阅的节点列表，供 `verge.profiles.proxies` 使用
+pub type ProxiesLookup = fn(&str) -> Option<Sequence>;
+
+/// 注入到脚本中的 `verge` 工具库
+const VERGE_LIB: &str = include_str!("./builtin/verge_lib.js");
+
+/// 脚本运行限制
+///
+/// `memory` 通过 `RLIMIT_DATA` 限制脚本子进程，只在 Linux 上生效；
+/// macOS 和 Windows 上该值会被忽略，失控的脚本只能由 `timeout` 结束
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptLimits {
+    /// 整个脚本的执行时间，超时后结束脚本子进程
+    pub timeout: Duration,
+    /// 单个循环的最大迭代次数
+    pub loop_iterations: u64,
+    /// 函数调用的最大递归深度
+    pub recursion: usize,
+    /// 虚拟机栈的最大长度
+    pub stack_size: usize,
+    /// 脚本子进程可分配的内存（字节），仅在 Linux 上生效
+    pub memory: u64,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            loop_iterations: 10_000_000,
+            recursion: 256,
+            stack_size: 1024,
+            memory: 512 * 1024 * 1024,
+        }
+    }
+}
+
+/// 脚本触发运行限制，写入 `chain_logs` 时级别为 `limit`
+#[derive(Debug)]
+pub enum ScriptError {
+    Timeout(Duration),
+    RuntimeLimit(String),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Timeout(timeout) => write!(
+                f,
+                "timeout: script did not finish within {}ms, the config before this script is used",
+                timeout.as_millis()
+            ),
+            ScriptError::RuntimeLimit(message) => write!(
+                f,
+                "runtime-limit: {message}, the config before this script is used"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+impl ScriptError {
+    pub fn to_log(&self) -> (String, String) {
+        ("limit".into(), self.to_string())
+    }
+}
+
+/// 发送给脚本子进程的任务
+#[derive(Serialize, Deserialize)]
+struct ScriptJob {
     script: String,
+    config: String,
+    name: String,
+    limits: ScriptLimits,
+    /// 是否提供 `verge.profiles.proxies`
+    profiles: bool,
+}
+
+/// 脚本子进程写到 stdout 的消息，每行一条
+#[derive(Serialize, Deserialize)]
+enum WorkerMessage {
+    Log(String, String),
+    /// 读取其他订阅的节点，父进程向 stdin 回复一行 JSON
+    Lookup(String),
+    Limit(String),
+    Done(Result<String, String>),
+}
+
+pub fn use_script(script: &str, config: Mapping, name: String) -> (Mapping, Vec<(String, String)>) {
+    use_script_with_limits(script, config, name, ScriptLimits::default())
+}
+
+/// 执行用户脚本，脚本可以通过 `verge.profiles.proxies` 读取其他订阅的节点
+pub fn use_script_with_profiles(
+    script: &str,
+    config: Mapping,
+    name: String,
+    lookup: ProxiesLookup,
+) -> (Mapping, Vec<(String, String)>) {
+    execute_script(script, config, name, ScriptLimits::default(), Some(lookup))
+}
+
+pub fn use_script_with_limits(
+    script: &str,
     config: Mapping,
     name: String,
-) -> Result<(Mapping, Vec<(String, String)>)> {
+    limits: ScriptLimits,
+) -> (Mapping, Vec<(String, String)>) {
+    execute_script(script, config, name, limits, None)
+}
+
+/// 在脚本子进程中执行脚本，出错或超出限制时返回原配置并在日志中记录原因
+fn execute_script(
+    script: &str,
+    config: Mapping,
+    name: String,
+    limits: ScriptLimits,
+    lookup: Option<ProxiesLookup>,
+) -> (Mapping, Vec<(String, String)>) {
+    let config = use_lowercase(config);
+    let mut out = vec![];
+
+    let result = serde_json::to_string(&config)
+        .map_err(Error::from)
+        .and_then(|config| {
+            let job = ScriptJob {
+                script: script.to_owned(),
+                config,
+                name,
+                limits,
+                profiles: lookup.is_some(),
+            };
+            spawn_script(job, lookup, &mut out)
+        });
+
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            match err.downcast_ref::<ScriptError>() {
+                Some(limit) => out.push(limit.to_log()),
+                None => out.push(("exception".into(), err.to_string())),
+            }
+            return (config, out);
+        }
+    };
+
+    if let Some(message) = result.strip_prefix("__error_flag__ ") {
+        out.push(("exception".into(), message.into()));
+        return (config, out);
+    }
+
+    // 直接解析JSON结果,不做其他解析
+    let res: Result<Mapping, Error> = parse_json_safely(&result);
+
+    match res {
+        Ok(config) => (use_lowercase(config), out),
+        Err(err) => {
+            out.push(("exception".into(), err.to_string()));
+            (config, out)
+        }
+    }
+}
+
+/// 启动脚本子进程执行任务，超时或出错时直接结束子进程，不会留下仍在运行的脚本
+fn spawn_script(
+    job: ScriptJob,
+    lookup: Option<ProxiesLookup>,
+    out: &mut Vec<(String, String)>,
+) -> Result<String> {
+    let (mut child, input, output) = start_worker()?;
+    let result = drive_worker(job, lookup, input, output, out);
+    if let Some(child) = child.as_mut() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    result
+}
+
+/// 以 `SCRIPT_WORKER_ARG` 启动自身，返回子进程及其 stdin / stdout
+#[cfg(not(test))]
+fn start_worker() -> Result<(
+    Option<std::process::Child>,
+    impl Write,
+    impl io::Read + Send + 'static,
+)> {
+    use std::process::{Command, Stdio};
+
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .arg(SCRIPT_WORKER_ARG)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let mut child = command.spawn()?;
+
+    let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        let _ = child.kill();
+        anyhow::bail!("failed to open the pipes of the script worker");
+    };
+    Ok((Some(child), stdin, stdout))
+}
+
+/// 测试程序无法作为脚本子进程启动，改为在线程中运行同一个 worker 循环，经管道通信
+#[cfg(test)]
+fn start_worker() -> Result<(
+    Option<std::process::Child>,
+    impl Write,
+    impl io::Read + Send + 'static,
+)> {
+    let (job_reader, job_writer) = io::pipe()?;
+    let (message_reader, message_writer) = io::pipe()?;
+    thread::spawn(move || {
+        let mut input = io::BufReader::new(job_reader);
+        if let Some(job) = read_job(&mut input) {
+            serve_job(job, input, message_writer);
+        }
+    });
+    Ok((None, job_writer, message_reader))
+}
+
+/// 把任务写给脚本子进程，收集日志并回复节点查询，直到脚本结束、超时或子进程退出
+fn drive_worker(
+    job: ScriptJob,
+    lookup: Option<ProxiesLookup>,
+    mut input: impl Write,
+    output: impl io::Read + Send + 'static,
+    out: &mut Vec<(String, String)>,
+) -> Result<String> {
+    let timeout = job.limits.timeout;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::BufReader::new(output).lines().map_while(Result::ok) {
+            let Ok(message) = serde_json::from_str::<WorkerMessage>(&line) else {
+                continue;
+            };
+            if tx.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    writeln!(input, "{}", serde_json::to_string(&job)?)?;
+    input.flush()?;
+    receive_result(&rx, timeout, out, |name| {
+        let proxies = lookup.and_then(|lookup| lookup(name));
+        writeln!(input, "{}", serde_json::to_string(&proxies)?)?;
+        input.flush()?;
+        Ok(())
+    })
+}
+
+/// 收集脚本日志并回复节点查询，直到脚本结束或超时
+fn receive_result(
+    rx: &mpsc::Receiver<WorkerMessage>,
+    timeout: Duration,
+    out: &mut Vec<(String, String)>,
+    mut reply: impl FnMut(&str) -> Result<()>,
+) -> Result<String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(remaining) {
+            Ok(WorkerMessage::Log(level, data)) => out.push((level, data)),
+            Ok(WorkerMessage::Lookup(name)) => reply(&name)?,
+            Ok(WorkerMessage::Limit(message)) => {
+                return Err(ScriptError::RuntimeLimit(message).into())
+            }
+            Ok(WorkerMessage::Done(result)) => return result.map_err(Error::msg),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                return Err(ScriptError::Timeout(timeout).into())
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                anyhow::bail!("script worker exited unexpectedly, it may exceed the memory limit")
+            }
+        }
+    }
+}
+
+/// 脚本子进程的入口，从 stdin 读取任务，日志和结果按行写到 stdout
+pub fn run_script_worker() {
+    let mut input = io::BufReader::new(io::stdin());
+    let Some(job) = read_job(&mut input) else {
+        return;
+    };
+
+    #[cfg(target_os = "linux")]
+    {
+        let limit = libc::rlimit {
+            rlim_cur: job.limits.memory,
+            rlim_max: job.limits.memory,
+        };
+        // 只限制当前的子进程
+        unsafe { libc::setrlimit(libc::RLIMIT_DATA, &limit) };
+    }
+
+    serve_job(job, input, io::stdout());
+}
+
+fn read_job(input: &mut impl BufRead) -> Option<ScriptJob> {
+    let mut line = String::new();
+    input.read_line(&mut line).ok()?;
+    serde_json::from_str(&line).ok()
+}
+
+/// 执行任务，日志、节点查询和结果按行写到 `output`，节点查询的回复从 `input` 读取
+fn serve_job<R, W>(job: ScriptJob, input: R, output: W)
+where
+    R: BufRead + Send + 'static,
+    W: Write + Send + 'static,
+{
+    let worker = thread::Builder::new()
+        .stack_size(SCRIPT_STACK_SIZE)
+        .spawn(move || {
+            let profiles = job.profiles;
+            let pipes = Rc::new(RefCell::new((input, output)));
+            let log = pipes.clone();
+            let lookup = pipes.clone();
+            let message = run_job(
+                job,
+                move |name| {
+                    if !profiles {
+                        return None;
+                    }
+                    let (input, output) = &mut *lookup.borrow_mut();
+                    send_message(output, &WorkerMessage::Lookup(name.into()));
+                    let mut line = String::new();
+                    input.read_line(&mut line).ok()?;
+                    serde_json::from_str(&line).ok()?
+                },
+                move |level, data| {
+                    send_message(&mut log.borrow_mut().1, &WorkerMessage::Log(level, data))
+                },
+            );
+            send_message(&mut pipes.borrow_mut().1, &message);
+        });
+    if let Ok(worker) = worker {
+        let _ = worker.join();
+    }
+}
+
+fn send_message(output: &mut impl Write, message: &WorkerMessage) {
+    if let Ok(line) = serde_json::to_string(message) {
+        let _ = writeln!(output, "{line}");
+        let _ = output.flush();
+    }
+}
+
+fn run_job(
+    job: ScriptJob,
+    lookup: impl Fn(&str) -> Option<Sequence> + 'static,
+    log: impl Fn(String, String) + 'static,
+) -> WorkerMessage {
+    let ScriptJob {
+        script,
+        config,
+        name,
+        limits,
+        ..
+    } = job;
+    match run_script(script, config, name, limits, lookup, log) {
+        Ok(result) => WorkerMessage::Done(Ok(result)),
+        Err(err) => match err.downcast::<ScriptError>() {
+            Ok(ScriptError::RuntimeLimit(message)) => WorkerMessage::Limit(message),
+            Ok(err) => WorkerMessage::Done(Err(err.to_string())),
+            Err(err) => WorkerMessage::Done(Err(err.to_string())),
+        },
+    }
+}
+
+fn run_script(
+    script: String,
+    config_str: String,
+    name: String,
+    limits: ScriptLimits,
+    lookup: impl Fn(&str) -> Option<Sequence> + 'static,
+    log: impl Fn(String, String) + 'static,
+) -> Result<String> {
     use boa_engine::{native_function::NativeFunction, Context, JsValue, Source};
-    use std::sync::{Arc, Mutex};
     let mut context = Context::default();
 
-    let outputs = Arc::new(Mutex::new(vec![]));
+    let runtime_limits = context.runtime_limits_mut();
+    runtime_limits.set_loop_iteration_limit(limits.loop_iterations);
+    runtime_limits.set_recursion_limit(limits.recursion);
+    runtime_limits.set_stack_size_limit(limits.stack_size);
 
-    let copy_outputs = outputs.clone();
     unsafe {
         let _ = context.register_global_builtin_callable(
             "__verge_log__".into(),
@@ -24,8 +420,7 @@ pub fn use_script(
                     let level = level.to_std_string().unwrap();
                     let data = args.get(1).unwrap().to_string(context)?;
                     let data = data.to_std_string().unwrap();
-                    let mut out = copy_outputs.lock().unwrap();
-                    out.push((level, data));
+                    log(level, data);
                     Ok(JsValue::undefined())
                 },
             ),
@@ -42,8 +437,29 @@ pub fn use_script(
       });"#,
     ));
 
-    let config = use_lowercase(config.clone());
-    let config_str = serde_json::to_string(&config)?;
+    register_native(&mut context, "__verge_yaml_parse__", native_yaml_parse);
+    register_native(
+        &mut context,
+        "__verge_yaml_stringify__",
+        native_yaml_stringify,
+    );
+    register_native(
+        &mut context,
+        "__verge_base64_encode__",
+        native_base64_encode,
+    );
+    register_native(
+        &mut context,
+        "__verge_base64_decode__",
+        native_base64_decode,
+    );
+    register_native(&mut context, "__verge_url_parse__", native_url_parse);
+    register_native(&mut context, "__verge_profile_proxies__", move |name| {
+        serde_json::to_string(&lookup(name)).map_err(|e| e.to_string())
+    });
+    if let Err(err) = context.eval(Source::from_bytes(VERGE_LIB)) {
+        anyhow::bail!("failed to load verge library: {err}");
+    }
 
     // 仅处理 name 参数中的特殊字符
     let safe_name = escape_js_string_for_single_quote(&name);
@@ -57,29 +473,92 @@ pub fn use_script(
       }}"#
     );
 
-    if let Ok(result) = context.eval(Source::from_bytes(code.as_str())) {
-        if !result.is_string() {
-            anyhow::bail!("main function should return object");
-        }
-        let result = result.to_string(&mut context).unwrap();
-        let result = result.to_std_string().unwrap();
-
-        // 直接解析JSON结果,不做其他解析
-        let res: Result<Mapping, Error> = parse_json_safely(&result);
-
-        let mut out = outputs.lock().unwrap();
-        match res {
-            Ok(config) => Ok((use_lowercase(config), out.to_vec())),
-            Err(err) => {
-                out.push(("exception".into(), err.to_string()));
-                Ok((config, out.to_vec()))
+    match context.eval(Source::from_bytes(code.as_str())) {
+        Ok(result) => {
+            if !result.is_string() {
+                anyhow::bail!("main function should return object");
             }
+            let result = result.to_string(&mut context).unwrap();
+            Ok(result.to_std_string().unwrap())
         }
-    } else {
-        anyhow::bail!("main function should return object");
+        // 运行限制错误无法被脚本中的 try/catch 捕获
+        Err(err) if err.as_native().is_some_and(|e| e.is_runtime_limit()) => {
+            Err(ScriptError::RuntimeLimit(err.to_string()).into())
+        }
+        Err(_) => anyhow::bail!("main function should return object"),
     }
 }
 
+/// 注册一个接收单个字符串参数的原生函数，出错时在脚本中抛出 TypeError
+fn register_native<F>(context: &mut boa_engine::Context, name: &str, f: F)
+where
+    F: Fn(&str) -> Result<String, String> + 'static,
+{
+    use boa_engine::{native_function::NativeFunction, Context, JsNativeError, JsString, JsValue};
+
+    // 闭包不持有任何 JS 对象，无需被垃圾回收追踪
+    let function = unsafe {
+        NativeFunction::from_closure(
+            move |_: &JsValue, args: &[JsValue], context: &mut Context| {
+                let input = args
+                    .first()
+                    .cloned()
+                    .unwrap_or_default()
+                    .to_string(context)?
+                    .to_std_string_escaped();
+                match f(&input) {
+                    Ok(output) => Ok(JsValue::from(JsString::from(output.as_str()))),
+                    Err(err) => Err(JsNativeError::typ().with_message(err).into()),
+                }
+            },
+        )
+    };
+    let _ = context.register_global_builtin_callable(name.into(), 1, function);
+}
+
+/// YAML 文本转为 JSON 文本
+fn native_yaml_parse(text: &str) -> Result<String, String> {
+    let value: serde_yaml::Value = serde_yaml::from_str(text).map_err(|e| e.to_string())?;
+    serde_json::to_string(&value).map_err(|e| e.to_string())
+}
+
+/// JSON 文本转为 YAML 文本
+fn native_yaml_stringify(json: &str) -> Result<String, String> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    serde_yaml::to_string(&value).map_err(|e| e.to_string())
+}
+
+fn native_base64_encode(text: &str) -> Result<String, String> {
+    Ok(general_purpose::STANDARD.encode(text))
+}
+
+/// 与订阅解析共用解码规则，兼容 URL-safe 字母表、缺省的填充和换行
+fn native_base64_decode(text: &str) -> Result<String, String> {
+    decode_base64(text).ok_or_else(|| "invalid base64 or not UTF-8 text".into())
+}
+
+/// 解析 URL，字段与浏览器中的 `URL` 对象一致，另附解码后的查询参数 `params`
+fn native_url_parse(text: &str) -> Result<String, String> {
+    let url = url::Url::parse(text).map_err(|e| e.to_string())?;
+    let params: serde_json::Map<String, serde_json::Value> = url
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned().into()))
+        .collect();
+    let parsed = serde_json::json!({
+        "href": url.as_str(),
+        "protocol": format!("{}:", url.scheme()),
+        "username": url.username(),
+        "password": url.password().unwrap_or_default(),
+        "hostname": url.host_str().unwrap_or_default(),
+        "port": url.port().map(|p| p.to_string()).unwrap_or_default(),
+        "pathname": url.path(),
+        "search": url.query().map(|q| format!("?{q}")).unwrap_or_default(),
+        "hash": url.fragment().map(|f| format!("#{f}")).unwrap_or_default(),
+        "params": params,
+    });
+    Ok(parsed.to_string())
+}
+
 fn parse_json_safely(json_str: &str) -> Result<Mapping, Error> {
     let json_str = strip_outer_quotes(json_str);
 
@@ -125,7 +604,7 @@ fn test_script() {
   "#;
 
     let config = serde_yaml::from_str(config).unwrap();
-    let (config, results) = use_script(script.into(), config, "".to_string()).unwrap();
+    let (config, results) = use_script(script, config, "".to_string());
 
     let _ = serde_yaml::to_string(&config).unwrap();
     let yaml_config_size = std::mem::size_of_val(&config);
@@ -156,3 +635,245 @@ fn test_escape_unescape() {
     assert!(parsed_quoted.contains_key("key"));
     assert!(parsed_quoted.contains_key("nested"));
 }
+
+#[test]
+fn test_script_limits() {
+    let config = serde_yaml::from_str::<Mapping>("mode: rule").unwrap();
+
+    let infinite = r#"
+    function main(config) {
+      console.log("start");
+      while (true) {}
+      return config;
+    }
+  "#;
+    let limits = ScriptLimits {
+        loop_iterations: 1000,
+        ..ScriptLimits::default()
+    };
+    let (res, logs) = use_script_with_limits(infinite, config.clone(), "".into(), limits);
+    assert_eq!(res, config);
+    assert_eq!(logs.first().map(|l| l.0.as_str()), Some("log"));
+    assert!(logs.last().unwrap().0 == "limit");
+    assert!(logs.last().unwrap().1.starts_with("runtime-limit"));
+
+    let recursive = r#"
+    function f(n) { return f(n + 1); }
+    function main(config) {
+      try { f(0); } catch (e) {}
+      return config;
+    }
+  "#;
+    let (_, logs) = use_script(recursive, config.clone(), "".into());
+    assert_eq!(logs.last().unwrap().0, "limit");
+
+    let limits = ScriptLimits {
+        timeout: Duration::from_millis(100),
+        ..ScriptLimits::default()
+    };
+    let (res, logs) = use_script_with_limits(infinite, config.clone(), "".into(), limits);
+    assert_eq!(res, config);
+    assert!(logs.last().unwrap().1.starts_with("timeout"));
+
+    let throws = r#"
+    function main(config) { throw new Error("boom"); }
+  "#;
+    let (res, logs) = use_script(throws, config.clone(), "".into());
+    assert_eq!(res, config);
+    assert_eq!(
+        logs.last().unwrap(),
+        &("exception".into(), "Error: boom".into())
+    );
+}
+
+#[test]
+fn test_verge_natives() {
+    let json = native_yaml_parse("a: 1\nb: [x, y]").unwrap();
+    assert_eq!(json, r#"{"a":1,"b":["x","y"]}"#);
+    assert_eq!(
+        native_yaml_stringify(&json).unwrap(),
+        "a: 1\nb:\n- x\n- y\n"
+    );
+    assert!(native_yaml_parse("a: [").is_err());
+
+    let encoded = native_base64_encode("ss://节点?").unwrap();
+    assert_eq!(native_base64_decode(&encoded).unwrap(), "ss://节点?");
+    let url_safe = encoded.replace('+', "-").replace('/', "_");
+    assert_eq!(
+        native_base64_decode(url_safe.trim_end_matches('=')).unwrap(),
+        "ss://节点?"
+    );
+
+    let url =
+        native_url_parse("trojan://pass@example.com:443?sni=a.com&type=ws#%E8%8A%82%E7%82%B9")
+            .unwrap();
+    let url: serde_json::Value = serde_json::from_str(&url).unwrap();
+    assert_eq!(url["protocol"], "trojan:");
+    assert_eq!(url["username"], "pass");
+    assert_eq!(url["hostname"], "example.com");
+    assert_eq!(url["port"], "443");
+    assert_eq!(url["params"]["sni"], "a.com");
+    assert_eq!(url["hash"], "#%E8%8A%82%E7%82%B9");
+}
+
+#[test]
+fn test_verge_library() {
+    fn lookup(name: &str) -> Option<Sequence> {
+        (name == "other").then(|| serde_yaml::from_str("[{name: HK 01}, {name: US 01}]").unwrap())
+    }
+
+    let script = r#"
+    function main(config) {
+      const other = verge.profiles.proxies("other");
+      config.proxies = verge.proxies.filter(other, /hk/i);
+      config.names = verge.proxies.names(verge.proxies.rename(other, / 0/, "-"));
+      config.missing = verge.profiles.proxies("none");
+      config.version = verge.version;
+      config.host = verge.url.parse("http://a.com:8080/p?x=1").hostname;
+      config.decoded = verge.base64.decode(verge.base64.encode("hi"));
+      config.parsed = verge.yaml.parse("k: v").k;
+      return config;
+    }
+  "#;
+    let (config, logs) = use_script_with_profiles(script, Mapping::new(), "".into(), lookup);
+    assert!(logs.is_empty(), "{logs:?}");
+
+    let expected = r#"
+    proxies:
+      - name: HK 01
+    names: [HK-1, US-1]
+    missing: null
+    version: 1.0.0
+    host: a.com
+    decoded: hi
+    parsed: v
+  "#;
+    assert_eq!(config, serde_yaml::from_str::<Mapping>(expected).unwrap());
+}
+
+#[cfg(test)]
+fn test_job(script: &str, limits: ScriptLimits) -> ScriptJob {
+    ScriptJob {
+        script: script.into(),
+        config: "{}".into(),
+        name: "".into(),
+        limits,
+        profiles: true,
+    }
+}
+
+#[test]
+fn test_worker_protocol() {
+    let script = r#"
+    function main(config) {
+      console.log("hi");
+      config.names = verge.proxies.names(verge.profiles.proxies("other"));
+      return config;
+    }
+  "#;
+    let job = test_job(script, ScriptLimits::default());
+
+    // 任务之后紧跟节点查询的回复
+    let mut input = serde_json::to_string(&job).unwrap() + "\n";
+    input += "[{\"name\":\"HK 01\"}]\n";
+    let mut input = io::Cursor::new(input.into_bytes());
+    let job = read_job(&mut input).unwrap();
+
+    let (reader, writer) = io::pipe().unwrap();
+    serve_job(job, input, writer);
+    let lines: Vec<_> = io::BufReader::new(reader)
+        .lines()
+        .map(|line| serde_json::from_str::<WorkerMessage>(&line.unwrap()).unwrap())
+        .collect();
+
+    assert!(matches!(&lines[..], [
+        WorkerMessage::Log(level, _),
+        WorkerMessage::Lookup(name),
+        WorkerMessage::Done(Ok(result)),
+    ] if level == "log" && name == "other" && result.contains("HK 01")));
+}
+
+#[test]
+fn test_worker_failures() {
+    fn lookup(_: &str) -> Option<Sequence> {
+        None
+    }
+
+    // 启动一个假的子进程，读取任务后写出 `lines`，`hang` 时保持管道不关闭
+    fn drive(
+        lines: &'static [&'static str],
+        hang: bool,
+    ) -> (Result<String>, Vec<(String, String)>) {
+        let limits = ScriptLimits {
+            timeout: Duration::from_millis(200),
+            ..ScriptLimits::default()
+        };
+        let (job_reader, job_writer) = io::pipe().unwrap();
+        let (message_reader, mut message_writer) = io::pipe().unwrap();
+        thread::spawn(move || {
+            let mut input = io::BufReader::new(job_reader);
+            assert!(read_job(&mut input).is_some());
+            for line in lines {
+                writeln!(message_writer, "{line}").unwrap();
+            }
+            if hang {
+                thread::sleep(Duration::from_secs(2));
+            }
+        });
+        let mut out = vec![];
+        let job = test_job("", limits);
+        let result = drive_worker(job, Some(lookup), job_writer, message_reader, &mut out);
+        (result, out)
+    }
+
+    // 无法解析的行被忽略，子进程无响应时超时
+    let (result, out) = drive(&["garbage", r#"{"Log":["log","start"]}"#], true);
+    let err = result.unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(ScriptError::Timeout(_))));
+    assert_eq!(out, vec![("log".into(), "start".into())]);
+
+    // 子进程超出内存限制被系统结束，管道在没有结果时关闭
+    let (result, out) = drive(&[r#"{"Log":["log","start"]}"#], false);
+    assert!(result.unwrap_err().to_string().contains("memory limit"));
+    assert_eq!(out.len(), 1);
+
+    let (result, _) = drive(&[r#"{"Limit":"loop limit"}"#], false);
+    assert!(matches!(
+        result.unwrap_err().downcast_ref(),
+        Some(ScriptError::RuntimeLimit(message)) if message == "loop limit"
+    ));
+
+    let (result, _) = drive(&[r#"{"Done":{"Ok":"{}"}}"#], false);
+    assert_eq!(result.unwrap(), "{}");
+}
+
+#[test]
+fn test_verge_rename_in_config() {
+    let script = r#"
+    function main(config) {
+      return verge.proxies.renameInConfig(config, /^HK/, "香港");
+    }
+  "#;
+    let config = r#"
+    proxies:
+      - {name: HK 01}
+      - {name: US 01}
+    proxy-groups:
+      - {name: HK Auto, proxies: [HK 01]}
+      - {name: Select, proxies: [HK Auto, HK 01, US 01, DIRECT, REJECT]}
+  "#;
+    let config = serde_yaml::from_str(config).unwrap();
+    let (config, logs) = use_script(script, config, "".into());
+    assert!(logs.is_empty(), "{logs:?}");
+
+    // 只改节点及其引用，代理组名和内置出站不变
+    let expected = r#"
+    proxies:
+      - {name: 香港 01}
+      - {name: US 01}
+    proxy-groups:
+      - {name: HK Auto, proxies: [香港 01]}
+      - {name: Select, proxies: [HK Auto, 香港 01, US 01, DIRECT, REJECT]}
+  "#;
+    assert_eq!(config, serde_yaml::from_str::<Mapping>(expected).unwrap());
+}
@@ -1,81 +1,163 @@
+use super::use_merge;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_yaml::{Mapping, Sequence, Value};
+use std::collections::HashSet;
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct SeqMap {
+    #[serde(default)]
     pub prepend: Sequence,
+    #[serde(default)]
     pub append: Sequence,
+    /// 按名称删除（规则按整行）
+    #[serde(default)]
     pub delete: Vec<String>,
+    /// 名称（规则按整行）匹配任一正则即删除
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub delete_regex: Vec<String>,
+    /// 字段全部相等即删除，如 `{ type: ss }`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub delete_match: Vec<Mapping>,
+    /// 插入到指定条目之前或之后
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub insert: Vec<SeqInsert>,
+    /// 条目名称 -> 要修改的字段，按 Merge 的规则合并，支持 `append-proxies` 等指令
+    #[serde(default, skip_serializing_if = "Mapping::is_empty")]
+    pub patch: Mapping,
 }
 
-pub fn use_seq(seq: SeqMap, mut config: Mapping, field: &str) -> Mapping {
+#[derive(Debug, Clone, Default, Hash, Serialize, Deserialize)]
+pub struct SeqInsert {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(default)]
+    pub items: Sequence,
+}
+
+/// 代理和代理组取 `name`，规则取整行
+fn item_name(item: &Value) -> Option<&str> {
+    match item {
+        Value::String(s) => Some(s),
+        Value::Mapping(m) => m.get("name").and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+fn is_field_match(item: &Value, pattern: &Mapping) -> bool {
+    match item {
+        Value::Mapping(m) => {
+            !pattern.is_empty() && pattern.iter().all(|(k, v)| m.get(k) == Some(v))
+        }
+        _ => false,
+    }
+}
+
+fn compile_regex(patterns: &[String]) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|pattern| match Regex::new(pattern) {
+            Ok(re) => Some(re),
+            Err(err) => {
+                log::warn!(target: "app", "invalid delete-regex `{pattern}`: {err}");
+                None
+            }
+        })
+        .collect()
+}
+
+fn apply_insert(seq: &mut Sequence, insert: &SeqInsert) {
+    let position = |anchor: &str| seq.iter().position(|item| item_name(item) == Some(anchor));
+    let index = match (insert.before.as_deref(), insert.after.as_deref()) {
+        (Some(before), _) => position(before),
+        (None, Some(after)) => position(after).map(|i| i + 1),
+        (None, None) => None,
+    };
+    // 找不到参照条目时追加到末尾
+    let index = index.unwrap_or(seq.len());
+    seq.splice(index..index, insert.items.iter().cloned());
+}
+
+pub fn use_seq(seq: &SeqMap, mut config: Mapping, field: &str) -> Mapping {
     let SeqMap {
         prepend,
         append,
         delete,
+        delete_regex,
+        delete_match,
+        insert,
+        patch,
     } = seq;
 
+    let delete_regex = compile_regex(delete_regex);
+    let should_delete = |item: &Value| {
+        if let Some(name) = item_name(item) {
+            if delete.iter().any(|d| d == name) || delete_regex.iter().any(|re| re.is_match(name)) {
+                return true;
+            }
+        }
+        delete_match
+            .iter()
+            .any(|pattern| is_field_match(item, pattern))
+    };
+
+    // 实际被删除的名称，用于清理代理组中的引用
+    let mut deleted: HashSet<String> = delete.iter().cloned().collect();
+
     let mut new_seq = Sequence::new();
-    new_seq.extend(prepend);
+    new_seq.extend(prepend.iter().cloned());
 
-    if let Some(Value::Sequence(origin)) = config.get(field) {
+    // 取出原列表而不是复制，规则很多时可以省下一次完整的复制
+    if let Some(Value::Sequence(origin)) = config.get_mut(field) {
         // Filter out deleted items
-        let filtered: Sequence = origin
-            .iter()
-            .filter(|item| {
-                if let Value::String(s) = item {
-                    !delete.contains(s)
-                } else if let Value::Mapping(m) = item {
-                    if let Some(Value::String(name)) = m.get("name") {
-                        !delete.contains(name)
-                    } else {
-                        true
-                    }
-                } else {
-                    true
+        for item in std::mem::take(origin) {
+            if should_delete(&item) {
+                if let Some(name) = item_name(&item) {
+                    deleted.insert(name.to_string());
                 }
-            })
-            .cloned()
-            .collect();
-        new_seq.extend(filtered);
+            } else {
+                new_seq.push(item);
+            }
+        }
+    }
+
+    new_seq.extend(append.iter().cloned());
+
+    for each in insert {
+        apply_insert(&mut new_seq, each);
+    }
+
+    if !patch.is_empty() {
+        for item in new_seq.iter_mut() {
+            let Value::Mapping(map) = item else {
+                continue;
+            };
+            let fields = map
+                .get("name")
+                .and_then(|name| patch.get(name))
+                .and_then(Value::as_mapping);
+            if let Some(fields) = fields {
+                *map = use_merge(fields, std::mem::take(map));
+            }
+        }
     }
 
-    new_seq.extend(append);
     config.insert(Value::String(field.into()), Value::Sequence(new_seq));
 
     // If this is proxies field, we also need to filter proxy-groups
     if field == "proxies" {
         if let Some(Value::Sequence(groups)) = config.get_mut("proxy-groups") {
-            let mut new_groups = Sequence::new();
-            for group in groups {
-                if let Value::Mapping(group_map) = group {
-                    let mut new_group = group_map.clone();
-                    if let Some(Value::Sequence(proxies)) = group_map.get("proxies") {
-                        let filtered_proxies: Sequence = proxies
-                            .iter()
-                            .filter(|p| {
-                                if let Value::String(name) = p {
-                                    !delete.contains(name)
-                                } else {
-                                    true
-                                }
-                            })
-                            .cloned()
-                            .collect();
-                        new_group.insert(
-                            Value::String("proxies".into()),
-                            Value::Sequence(filtered_proxies),
-                        );
-                    }
-                    new_groups.push(Value::Mapping(new_group));
-                } else {
-                    new_groups.push(group.clone());
+            for group in groups.iter_mut() {
+                if let Some(Value::Sequence(proxies)) = group.get_mut("proxies") {
+                    proxies.retain(|p| match p {
+                        Value::String(name) => !deleted.contains(name),
+                        _ => true,
+                    });
                 }
             }
-            config.insert(
-                Value::String("proxy-groups".into()),
-                Value::Sequence(new_groups),
-            );
         }
     }
 
@@ -113,9 +195,10 @@ proxy-groups:
             prepend: Sequence::new(),
             append: Sequence::new(),
             delete: vec!["proxy1".to_string()],
+            ..SeqMap::default()
         };
 
-        config = use_seq(seq, config, "proxies");
+        config = use_seq(&seq, config, "proxies");
 
         // Check if proxy1 is removed from proxies
         let proxies = config.get("proxies").unwrap().as_sequence().unwrap();
@@ -152,4 +235,98 @@ proxy-groups:
         assert_eq!(group1_proxies[0].as_str().unwrap(), "proxy2");
         assert_eq!(group2_proxies.len(), 0);
     }
+
+    #[test]
+    fn test_delete_by_regex_and_field() {
+        let config_str = r#"
+proxies:
+- { name: "HK 01", type: ss }
+- { name: "HK 02", type: vmess }
+- { name: "US 01", type: ss }
+- { name: "JP 01", type: trojan }
+proxy-groups:
+- name: "auto"
+  type: url-test
+  proxies: ["HK 01", "HK 02", "US 01", "JP 01"]
+"#;
+        let config: Mapping = serde_yaml::from_str(config_str).unwrap();
+
+        let seq: SeqMap = serde_yaml::from_str(
+            r#"
+delete-regex: ["^HK"]
+delete-match:
+  - { type: trojan }
+"#,
+        )
+        .unwrap();
+        let config = use_seq(&seq, config, "proxies");
+
+        let names: Vec<&str> = config["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .filter_map(item_name)
+            .collect();
+        assert_eq!(names, vec!["US 01"]);
+
+        let group_proxies = config["proxy-groups"][0]["proxies"].as_sequence().unwrap();
+        assert_eq!(group_proxies, &vec![Value::from("US 01")]);
+    }
+
+    #[test]
+    fn test_insert_and_patch() {
+        let config_str = r#"
+rules:
+- DOMAIN,a.com,DIRECT
+- GEOIP,CN,DIRECT
+- MATCH,PROXY
+proxy-groups:
+- { name: PROXY, type: select, proxies: [a] }
+"#;
+        let config: Mapping = serde_yaml::from_str(config_str).unwrap();
+
+        let rules: SeqMap = serde_yaml::from_str(
+            r#"
+insert:
+  - before: "GEOIP,CN,DIRECT"
+    items: ["DOMAIN,b.com,REJECT"]
+  - after: "MATCH,PROXY"
+    items: ["DOMAIN,d.com,DIRECT"]
+  - after: missing
+    items: ["DOMAIN,c.com,DIRECT"]
+"#,
+        )
+        .unwrap();
+        let config = use_seq(&rules, config, "rules");
+        assert_eq!(
+            config["rules"].as_sequence().unwrap(),
+            &[
+                "DOMAIN,a.com,DIRECT",
+                "DOMAIN,b.com,REJECT",
+                "GEOIP,CN,DIRECT",
+                "MATCH,PROXY",
+                "DOMAIN,d.com,DIRECT",
+                "DOMAIN,c.com,DIRECT",
+            ]
+            .map(Value::from)
+            .to_vec()
+        );
+
+        let groups: SeqMap = serde_yaml::from_str(
+            r#"
+patch:
+  PROXY:
+    udp: true
+    append-proxies: [b]
+"#,
+        )
+        .unwrap();
+        let config = use_seq(&groups, config, "proxy-groups");
+        let group = &config["proxy-groups"][0];
+        assert_eq!(group["udp"], Value::from(true));
+        assert_eq!(
+            group["proxies"].as_sequence().unwrap(),
+            &vec![Value::from("a"), Value::from("b")]
+        );
+    }
 }
@@ -0,0 +1,367 @@
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+/// 列表项展示的最大长度
+const MAX_VALUE_LEN: usize = 120;
+
+/// 列表中被插入或删除的一项
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ListChange {
+    pub path: String,
+    pub index: usize,
+    pub value: String,
+}
+
+/// 两份配置之间的结构化差异，键路径用 `.` 连接
+///
+/// 列表只比较项的增删，调整顺序不会被记录
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ConfigDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub inserted: Vec<ListChange>,
+    pub deleted: Vec<ListChange>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.inserted.is_empty()
+            && self.deleted.is_empty()
+    }
+}
+
+/// 增强流程中的一步及其造成的变化
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct EnhanceStep {
+    pub step: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+    pub diff: ConfigDiff,
+}
+
+/// 列表中一项的 hash 和展示文本
+struct ListItem {
+    hash: u64,
+    display: String,
+}
+
+impl ListItem {
+    fn new(value: &Value) -> Self {
+        Self {
+            hash: hash_value(value),
+            display: display_value(value),
+        }
+    }
+}
+
+/// 上一步配置中一个顶层键的摘要
+enum Entry {
+    /// 顶层列表（rules、proxies 等）可能很长，只保存每一项的摘要
+    List(Vec<ListItem>),
+    Value(Value),
+}
+
+struct KeySnapshot {
+    key: Value,
+    hash: u64,
+    entry: Entry,
+}
+
+impl KeySnapshot {
+    fn new(key: &Value, value: &Value, hash: u64) -> Self {
+        let entry = match value {
+            Value::Sequence(seq) => Entry::List(seq.iter().map(ListItem::new).collect()),
+            other => Entry::Value(other.clone()),
+        };
+        Self {
+            key: key.clone(),
+            hash,
+            entry,
+        }
+    }
+}
+
+/// 记录每一步执行前后的配置差异
+///
+/// 不保存整份配置的副本，每一步只比较 hash 发生变化的顶层键
+pub struct EnhanceTrace {
+    prev: Vec<KeySnapshot>,
+    steps: Vec<EnhanceStep>,
+}
+
+impl EnhanceTrace {
+    pub fn new(config: &Mapping) -> Self {
+        Self {
+            prev: config
+                .iter()
+                .map(|(key, value)| KeySnapshot::new(key, value, hash_value(value)))
+                .collect(),
+            steps: vec![],
+        }
+    }
+
+    pub fn record(&mut self, step: &str, uid: Option<&str>, config: &Mapping) {
+        let mut diff = ConfigDiff::default();
+        let mut prev = std::mem::take(&mut self.prev);
+        for snapshot in prev.iter() {
+            if !config.contains_key(&snapshot.key) {
+                diff.removed.push(key_path("", &snapshot.key));
+            }
+        }
+
+        let mut next = Vec::with_capacity(config.len());
+        for (key, value) in config.iter() {
+            let hash = hash_value(value);
+            let old = prev
+                .iter()
+                .position(|snapshot| &snapshot.key == key)
+                .map(|index| prev.swap_remove(index));
+            match old {
+                Some(old) if old.hash == hash => next.push(old),
+                Some(old) => {
+                    let path = key_path("", key);
+                    match (&old.entry, value) {
+                        (Entry::List(old), Value::Sequence(new)) => {
+                            let new = new.iter().map(ListItem::new).collect::<Vec<_>>();
+                            diff_items(&path, old, &new, &mut diff);
+                            next.push(KeySnapshot {
+                                key: key.clone(),
+                                hash,
+                                entry: Entry::List(new),
+                            });
+                            continue;
+                        }
+                        (Entry::Value(old), new) => diff_value(&path, old, new, &mut diff),
+                        _ => diff.changed.push(path),
+                    }
+                    next.push(KeySnapshot::new(key, value, hash));
+                }
+                None => {
+                    diff.added.push(key_path("", key));
+                    next.push(KeySnapshot::new(key, value, hash));
+                }
+            }
+        }
+        self.prev = next;
+
+        self.steps.push(EnhanceStep {
+            step: step.into(),
+            uid: uid.filter(|uid| !uid.is_empty()).map(str::to_string),
+            diff,
+        });
+    }
+
+    pub fn into_steps(self) -> Vec<EnhanceStep> {
+        self.steps
+    }
+}
+
+fn hash_value(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn key_path(parent: &str, key: &Value) -> String {
+    let key = match key {
+        Value::String(key) => key.clone(),
+        other => display_value(other),
+    };
+    if parent.is_empty() {
+        key
+    } else {
+        format!("{parent}.{key}")
+    }
+}
+
+fn diff_mapping(path: &str, before: &Mapping, after: &Mapping, diff: &mut ConfigDiff) {
+    for (key, old) in before.iter() {
+        let key_path = key_path(path, key);
+        match after.get(key) {
+            None => diff.removed.push(key_path),
+            Some(new) => diff_value(&key_path, old, new, diff),
+        }
+    }
+    for key in after.keys() {
+        if !before.contains_key(key) {
+            diff.added.push(key_path(path, key));
+        }
+    }
+}
+
+fn diff_value(path: &str, old: &Value, new: &Value, diff: &mut ConfigDiff) {
+    match (old, new) {
+        (Value::Mapping(old), Value::Mapping(new)) => diff_mapping(path, old, new, diff),
+        (Value::Sequence(old), Value::Sequence(new)) => diff_sequence(path, old, new, diff),
+        _ if old != new => diff.changed.push(path.into()),
+        _ => {}
+    }
+}
+
+fn diff_sequence(path: &str, old: &[Value], new: &[Value], diff: &mut ConfigDiff) {
+    let old = old.iter().map(ListItem::new).collect::<Vec<_>>();
+    let new = new.iter().map(ListItem::new).collect::<Vec<_>>();
+    diff_items(path, &old, &new, diff);
+}
+
+/// 先去掉相同的首尾，再按出现次数比对中间部分，规则列表很长时也是线性的
+fn diff_items(path: &str, old: &[ListItem], new: &[ListItem], diff: &mut ConfigDiff) {
+    let same = |(a, b): &(&ListItem, &ListItem)| a.hash == b.hash;
+    let prefix = old.iter().zip(new).take_while(same).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(same)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut remaining: HashMap<u64, usize> = HashMap::new();
+    for item in new_mid {
+        *remaining.entry(item.hash).or_default() += 1;
+    }
+    for (i, item) in old_mid.iter().enumerate() {
+        match remaining.get_mut(&item.hash) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => diff.deleted.push(ListChange {
+                path: path.into(),
+                index: prefix + i,
+                value: item.display.clone(),
+            }),
+        }
+    }
+
+    let mut remaining: HashMap<u64, usize> = HashMap::new();
+    for item in old_mid {
+        *remaining.entry(item.hash).or_default() += 1;
+    }
+    for (i, item) in new_mid.iter().enumerate() {
+        match remaining.get_mut(&item.hash) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => diff.inserted.push(ListChange {
+                path: path.into(),
+                index: prefix + i,
+                value: item.display.clone(),
+            }),
+        }
+    }
+}
+
+/// 列表项的简短描述：字符串原样输出，代理和代理组只显示名称
+pub fn display_value(value: &Value) -> String {
+    let text = match value {
+        Value::String(s) => s.clone(),
+        Value::Mapping(map) if map.get("name").and_then(Value::as_str).is_some() => {
+            format!("name: {}", map["name"].as_str().unwrap_or_default())
+        }
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim_end()
+            .replace('\n', " "),
+    };
+    if text.chars().count() > MAX_VALUE_LEN {
+        let short: String = text.chars().take(MAX_VALUE_LEN).collect();
+        format!("{short}...")
+    } else {
+        text
+    }
+}
+
+#[test]
+fn test_diff_config() {
+    let before = serde_yaml::from_str::<Mapping>(
+        r#"
+        mode: rule
+        ipv6: true
+        dns:
+          enable: true
+          nameserver: [1.1.1.1, 8.8.8.8]
+        rules:
+          - DOMAIN,a.com,DIRECT
+          - DOMAIN,b.com,DIRECT
+          - MATCH,DIRECT
+        proxies:
+          - { name: a, type: ss }
+        "#,
+    )
+    .unwrap();
+    let after = serde_yaml::from_str::<Mapping>(
+        r#"
+        mode: global
+        dns:
+          enable: true
+          nameserver: [1.1.1.1, 8.8.8.8]
+          ipv6: false
+        rules:
+          - DOMAIN,x.com,REJECT
+          - DOMAIN,a.com,DIRECT
+          - MATCH,DIRECT
+        proxies:
+          - { name: a, type: ss }
+          - { name: b, type: vmess }
+        tun:
+          enable: true
+        "#,
+    )
+    .unwrap();
+
+    let mut trace = EnhanceTrace::new(&before);
+    trace.record("merge", None, &after);
+    trace.record("script", None, &after);
+    let steps = trace.into_steps();
+    let diff = &steps[0].diff;
+    assert_eq!(diff.changed, vec!["mode"]);
+    assert_eq!(diff.removed, vec!["ipv6"]);
+    assert_eq!(diff.added, vec!["dns.ipv6", "tun"]);
+    assert_eq!(
+        diff.deleted,
+        vec![ListChange {
+            path: "rules".into(),
+            index: 1,
+            value: "DOMAIN,b.com,DIRECT".into(),
+        }]
+    );
+    assert_eq!(
+        diff.inserted,
+        vec![
+            ListChange {
+                path: "rules".into(),
+                index: 0,
+                value: "DOMAIN,x.com,REJECT".into(),
+            },
+            ListChange {
+                path: "proxies".into(),
+                index: 1,
+                value: "name: b".into(),
+            },
+        ]
+    );
+
+    assert!(steps[1].diff.is_empty());
+}
+
+#[test]
+fn test_enhance_trace() {
+    let mut config = Mapping::new();
+    let mut trace = EnhanceTrace::new(&config);
+
+    config.insert("mode".into(), "rule".into());
+    trace.record("merge", Some("m1"), &config);
+    trace.record("script", Some(""), &config);
+
+    let steps = trace.into_steps();
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[0].uid.as_deref(), Some("m1"));
+    assert_eq!(steps[0].diff.added, vec!["mode"]);
+    assert_eq!(steps[1].uid, None);
+    assert!(steps[1].diff.is_empty());
+}
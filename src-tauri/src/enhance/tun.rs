@@ -56,20 +56,10 @@ pub async fn use_tun(mut config: Mapping, enable: bool) -> Mapping {
             if !dns_val.contains_key(Value::from("fake-ip-range")) {
                 revise!(dns_val, "fake-ip-range", "198.18.0.1/16");
             }
-
-            #[cfg(target_os = "macos")]
-            {
-                crate::utils::resolve::restore_public_dns().await;
-                crate::utils::resolve::set_public_dns("8.8.8.8".to_string()).await;
-            }
         }
 
         // 当TUN启用时，将修改后的DNS配置写回
         revise!(config, "dns", dns_val);
-    } else {
-        // TUN未启用时，仅恢复系统DNS，不修改配置文件中的DNS设置
-        #[cfg(target_os = "macos")]
-        crate::utils::resolve::restore_public_dns().await;
     }
 
     // 更新TUN配置
@@ -78,3 +68,24 @@ pub async fn use_tun(mut config: Mapping, enable: bool) -> Mapping {
 
     config
 }
+
+/// 按 tun 开关调整 macOS 的系统 DNS，fake-ip 模式下指向公共 DNS，关闭时恢复
+///
+/// 有副作用，不放在增强流程中，复用上次的运行时配置时也要执行
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+pub async fn sync_tun_dns(config: &Mapping, enable: bool) {
+    #[cfg(target_os = "macos")]
+    {
+        let fake_ip = config
+            .get("dns")
+            .and_then(|dns| dns.get("enhanced-mode"))
+            .and_then(|mode| mode.as_str())
+            .is_none_or(|mode| mode == "fake-ip");
+        if !enable {
+            crate::utils::resolve::restore_public_dns().await;
+        } else if fake_ip {
+            crate::utils::resolve::restore_public_dns().await;
+            crate::utils::resolve::set_public_dns("8.8.8.8".to_string()).await;
+        }
+    }
+}
@@ -40,12 +40,10 @@ fn after_change_clash_mode() {
     AsyncHandler::spawn(move || async {
         match MihomoManager::global().get_connections().await {
             Ok(connections) => {
-                if let Some(connections_array) = connections["connections"].as_array() {
-                    for connection in connections_array {
-                        if let Some(id) = connection["id"].as_str() {
-                            let _ = MihomoManager::global().delete_connection(id).await;
-                        }
-                    }
+                for connection in connections.connections {
+                    let _ = MihomoManager::global()
+                        .delete_connection(&connection.id)
+                        .await;
                 }
             }
             Err(err) => {
@@ -14,7 +14,10 @@ pub async fn patch_clash(patch: Mapping) -> Result<()> {
 
     let res = {
         // 激活订阅
-        if patch.get("secret").is_some() || patch.get("external-controller").is_some() {
+        if patch.get("secret").is_some()
+            || patch.get("external-controller").is_some()
+            || patch.get("external-controller-unix").is_some()
+        {
             Config::generate().await?;
             CoreManager::global().restart_core().await?;
         } else {
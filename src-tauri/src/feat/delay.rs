@@ -0,0 +1,169 @@
+use crate::{
+    core::handle,
+    logging, logging_error,
+    module::{
+        latency::{LatencyHistory, LatencyStats},
+        mihomo::MihomoManager,
+    },
+    utils::logging::Type,
+};
+use anyhow::{bail, Result};
+use futures::{stream, StreamExt};
+use serde::Serialize;
+use std::collections::HashSet;
+
+const DEFAULT_CONCURRENCY: usize = 10;
+const MAX_CONCURRENCY: usize = 64;
+const DEFAULT_TIMEOUT: i32 = 5000;
+
+/// 内置出站，测速没有意义
+const BUILTIN_TYPES: [&str; 6] = [
+    "Direct",
+    "Reject",
+    "RejectDrop",
+    "Compatible",
+    "Pass",
+    "Dns",
+];
+
+/// 单个节点的测试结果
+#[derive(Debug, Clone, Serialize)]
+pub struct DelayTestResult {
+    pub name: String,
+    pub delay: Option<u32>,
+    pub error: Option<String>,
+    pub stats: LatencyStats,
+}
+
+/// 推送给前端的进度，事件名 `verge://delay-test-progress`
+#[derive(Debug, Clone, Serialize)]
+struct DelayTestProgress<'a> {
+    done: usize,
+    total: usize,
+    name: &'a str,
+    delay: Option<u32>,
+    error: Option<&'a str>,
+}
+
+/// 收集待测节点：指定代理组时取组内的非组节点，否则取全部节点（含 provider）
+async fn collect_targets(group: Option<&str>) -> Result<Vec<String>> {
+    let mihomo = MihomoManager::global();
+    let proxies = mihomo.get_refresh_proxies().await?;
+    let is_node = |name: &str| match proxies.get(name) {
+        Some(proxy) => !proxy.is_group() && !BUILTIN_TYPES.contains(&proxy.proxy_type.as_str()),
+        // 不在 /proxies 中的是 provider 节点
+        None => true,
+    };
+
+    let mut seen = HashSet::new();
+    let mut targets = Vec::new();
+
+    if let Some(group) = group {
+        let Some(members) = proxies.get(group).and_then(|p| p.all.as_ref()) else {
+            bail!("proxy group `{group}` not found");
+        };
+        for name in members {
+            if is_node(name) && seen.insert(name.clone()) {
+                targets.push(name.clone());
+            }
+        }
+        return Ok(targets);
+    }
+
+    let mut names: Vec<&String> = proxies.proxies.keys().collect();
+    names.sort();
+    for name in names {
+        if is_node(name) && seen.insert(name.clone()) {
+            targets.push(name.clone());
+        }
+    }
+
+    let providers = mihomo.get_providers_proxies().await?;
+    for provider in providers.providers.values() {
+        // `default` 即配置文件中的 proxies，已经包含在上面
+        if provider.name == "default" {
+            continue;
+        }
+        for proxy in &provider.proxies {
+            if seen.insert(proxy.name.clone()) {
+                targets.push(proxy.name.clone());
+            }
+        }
+    }
+
+    Ok(targets)
+}
+
+/// 以有限并发批量测试节点延迟，逐个推送进度并写入延迟历史
+pub async fn batch_test_delay(
+    group: Option<String>,
+    concurrency: Option<usize>,
+    timeout: Option<i32>,
+    url: Option<String>,
+) -> Result<Vec<DelayTestResult>> {
+    let targets = collect_targets(group.as_deref()).await?;
+    let total = targets.len();
+    let concurrency = concurrency
+        .unwrap_or(DEFAULT_CONCURRENCY)
+        .clamp(1, MAX_CONCURRENCY);
+    let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+
+    logging!(
+        info,
+        Type::Core,
+        true,
+        "Batch delay test started: {} nodes, concurrency {}",
+        total,
+        concurrency
+    );
+
+    let mihomo = MihomoManager::global();
+    let mut tests = stream::iter(targets)
+        .map(|name| {
+            let mihomo = mihomo.clone();
+            let url = url.clone();
+            async move {
+                let result = mihomo.test_proxy_delay(&name, url, timeout).await;
+                (name, result)
+            }
+        })
+        .buffer_unordered(concurrency);
+
+    let mut results = Vec::with_capacity(total);
+    while let Some((name, result)) = tests.next().await {
+        // 只记录内核返回的测速结果，连接控制器失败等错误不计入丢包
+        let (delay, error, tested) = match result {
+            // 内核以 0 表示超时
+            Ok(res) if res.delay > 0 => (Some(res.delay), None, true),
+            Ok(_) => (None, Some("timeout".to_string()), true),
+            Err(e) => (None, Some(e.to_string()), false),
+        };
+
+        let stats = {
+            let mut history = LatencyHistory::global().lock();
+            if tested {
+                history.record(&name, delay);
+            }
+            history.stats(&name)
+        };
+
+        handle::Handle::notify_delay_test_progress(&DelayTestProgress {
+            done: results.len() + 1,
+            total,
+            name: &name,
+            delay,
+            error: error.as_deref(),
+        });
+
+        results.push(DelayTestResult {
+            name,
+            delay,
+            error,
+            stats,
+        });
+    }
+
+    logging_error!(Type::Core, true, LatencyHistory::global().lock().save());
+
+    Ok(results)
+}
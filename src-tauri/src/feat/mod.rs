@@ -1,14 +1,20 @@
 mod backup;
 mod clash;
 mod config;
+mod delay;
 mod profile;
 mod proxy;
+mod selection;
+mod stream;
 mod window;
 
 // Re-export all functions from modules
 pub use backup::*;
 pub use clash::*;
 pub use config::*;
+pub use delay::*;
 pub use profile::*;
 pub use proxy::*;
+pub use selection::*;
+pub use stream::*;
 pub use window::*;
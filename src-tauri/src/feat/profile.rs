@@ -1,6 +1,6 @@
 use crate::{
     cmd,
-    config::{Config, PrfItem, PrfOption},
+    config::{Config, IProfiles, PrfItem, PrfOption},
     core::{handle, CoreManager, *},
     logging,
     process::AsyncHandler,
@@ -42,12 +42,17 @@ pub async fn update_profile(
 
     let url_opt = {
         let profiles = Config::profiles();
-        let profiles = profiles.latest();
+        let mut profiles = profiles.latest();
         let item = profiles.get_item(&uid)?;
         let is_remote = item.itype.as_ref().is_some_and(|s| s == "remote");
 
-        if !is_remote {
+        if item.itype.as_deref() == Some("aggregate") {
+            log::info!(target: "app", "[Subscription Update] {uid} is an aggregate subscription, regenerating");
+            profiles.regenerate_aggregate(&uid)?;
+            None
+        } else if !is_remote {
             log::info!(target: "app", "[Subscription Update] {uid} is not a remote subscription, skipping update");
+            regenerate_aggregates(&mut profiles, &uid);
             None // 非远程订阅直接更新
         } else if item.url.is_none() {
             log::warn!(target: "app", "[Subscription Update] {uid} is missing URL, cannot update");
@@ -58,26 +63,32 @@ pub async fn update_profile(
                 uid,
                 item.url.clone().unwrap()
             );
-            Some((item.url.clone().unwrap(), item.option.clone()))
+            Some((item.url.clone().unwrap(), item.option.clone(), item.clone()))
         }
     };
 
     let should_update = match url_opt {
-        Some((url, opt)) => {
+        Some((url, opt, cached)) => {
             log::info!(target: "app", "[Subscription Update] Start downloading new subscription content");
             let merged_opt = PrfOption::merge(opt.clone(), option.clone());
 
             // 尝试使用正常设置更新
-            match PrfItem::from_url(&url, None, None, merged_opt.clone()).await {
+            match PrfItem::from_url(&url, None, None, merged_opt.clone(), Some(&cached)).await {
                 Ok(item) => {
                     log::info!(target: "app", "[Subscription Update] Subscription config updated successfully");
                     let profiles = Config::profiles();
                     let mut profiles = profiles.latest();
+                    let changed = item.file_data.is_some();
                     profiles.update_item(uid.clone(), item)?;
 
-                    let is_current = Some(uid.clone()) == profiles.get_current();
-                    log::info!(target: "app", "[Subscription Update] Is current active subscription: {is_current}");
-                    is_current && auto_refresh
+                    if changed {
+                        let is_current = regenerate_aggregates(&mut profiles, &uid);
+                        log::info!(target: "app", "[Subscription Update] Is current active subscription: {is_current}");
+                        is_current && auto_refresh
+                    } else {
+                        log::info!(target: "app", "[Subscription Update] Subscription content unchanged, skipping core reload");
+                        false
+                    }
                 }
                 Err(err) => {
                     // 首次更新失败，尝试使用Clash代理
@@ -104,7 +115,9 @@ pub async fn update_profile(
                         });
 
                     // 使用Clash代理重试
-                    match PrfItem::from_url(&url, None, None, Some(fallback_opt)).await {
+                    match PrfItem::from_url(&url, None, None, Some(fallback_opt), Some(&cached))
+                        .await
+                    {
                         Ok(mut item) => {
                             log::info!(target: "app", "[Subscription Update] Update via Clash proxy succeeded");
 
@@ -117,6 +130,7 @@ pub async fn update_profile(
                             // 更新到配置
                             let profiles = Config::profiles();
                             let mut profiles = profiles.latest();
+                            let changed = item.file_data.is_some();
                             profiles.update_item(uid.clone(), item.clone())?;
 
                             // 获取配置名称用于通知
@@ -125,9 +139,14 @@ pub async fn update_profile(
                             // 发送通知告知用户自动更新使用了回退机制
                             handle::Handle::notice_message("update_with_clash_proxy", profile_name);
 
-                            let is_current = Some(uid.clone()) == profiles.get_current();
-                            log::info!(target: "app", "[Subscription Update] Is current active subscription: {is_current}");
-                            is_current && auto_refresh
+                            if changed {
+                                let is_current = regenerate_aggregates(&mut profiles, &uid);
+                                log::info!(target: "app", "[Subscription Update] Is current active subscription: {is_current}");
+                                is_current && auto_refresh
+                            } else {
+                                log::info!(target: "app", "[Subscription Update] Subscription content unchanged, skipping core reload");
+                                false
+                            }
                         }
                         Err(retry_err) => {
                             log::error!(target: "app", "[Subscription Update] Update via Clash proxy still failed: {retry_err}");
@@ -178,6 +197,17 @@ pub async fn update_profile(
     Ok(())
 }
 
+/// 重新生成引用该订阅的聚合订阅，返回当前订阅是否为该订阅或受影响的聚合订阅
+fn regenerate_aggregates(profiles: &mut IProfiles, uid: &str) -> bool {
+    let regenerated = profiles.regenerate_aggregates(uid);
+    if !regenerated.is_empty() {
+        log::info!(target: "app", "[Subscription Update] Regenerated aggregate subscriptions: {}", regenerated.join(", "));
+    }
+    profiles
+        .get_current()
+        .is_some_and(|current| current == uid || regenerated.contains(&current))
+}
+
 /// 增强配置
 pub async fn enhance_profiles() -> Result<()> {
     crate::core::CoreManager::global()
@@ -0,0 +1,152 @@
+use crate::{
+    config::{Config, PrfItem, PrfSelected},
+    logging, logging_error,
+    module::mihomo::MihomoManager,
+    process::AsyncHandler,
+    utils::logging::Type,
+};
+use anyhow::Result;
+use mihomo_api::Proxies;
+use std::{collections::HashMap, time::Duration};
+
+/// 内核刚启动时控制器可能还未就绪，最多等待约 5 秒
+const READY_RETRIES: u32 = 10;
+const READY_INTERVAL: Duration = Duration::from_millis(500);
+
+async fn fetch_proxies() -> Result<Proxies> {
+    let mut attempt = 0;
+    loop {
+        match MihomoManager::global().get_refresh_proxies().await {
+            Ok(proxies) => return Ok(proxies),
+            Err(e) if e.is_transport() && attempt < READY_RETRIES => {
+                attempt += 1;
+                tokio::time::sleep(READY_INTERVAL).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// 与 `save_selected` 使用同一份数据，保证读到的就是上次写入的选择
+fn current_selected() -> Option<(String, Vec<PrfSelected>)> {
+    let profiles = Config::profiles();
+    let profiles = profiles.data();
+    let uid = profiles.get_current()?;
+    let selected = profiles
+        .get_item(&uid)
+        .ok()
+        .and_then(|item| item.selected.clone())
+        .unwrap_or_default();
+    Some((uid, selected))
+}
+
+fn to_map(selected: &[PrfSelected]) -> HashMap<&str, &str> {
+    selected
+        .iter()
+        .filter_map(|s| Some((s.name.as_deref()?, s.now.as_deref()?)))
+        .collect()
+}
+
+fn save_selected(uid: String, selected: Vec<PrfSelected>) -> Result<()> {
+    let item = PrfItem {
+        selected: Some(selected),
+        ..PrfItem::default()
+    };
+    Config::profiles().data().patch_item(uid, item)
+}
+
+/// 把当前订阅保存的代理组选择重新应用到内核，并记录实际生效的选择
+///
+/// 只处理 select 类型的代理组，已不存在的节点会被跳过
+pub async fn restore_proxy_selections() -> Result<()> {
+    let Some((uid, saved)) = current_selected() else {
+        return Ok(());
+    };
+    let proxies = fetch_proxies().await?;
+    let saved_map = to_map(&saved);
+
+    let mut groups: Vec<_> = proxies
+        .groups()
+        .filter(|group| group.proxy_type == "Selector")
+        .collect();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut selected = Vec::with_capacity(groups.len());
+    for group in groups {
+        let mut now = group.now.clone();
+
+        if let Some(&target) = saved_map.get(group.name.as_str()) {
+            let exists = group
+                .all
+                .as_ref()
+                .is_some_and(|all| all.iter().any(|name| name == target));
+
+            if !exists {
+                logging!(
+                    info,
+                    Type::Core,
+                    true,
+                    "Skip restoring `{}`: node `{}` no longer exists",
+                    group.name,
+                    target
+                );
+            } else if now.as_deref() != Some(target) {
+                match MihomoManager::global()
+                    .select_proxy(&group.name, target)
+                    .await
+                {
+                    Ok(_) => now = Some(target.to_string()),
+                    Err(e) => logging!(
+                        warn,
+                        Type::Core,
+                        true,
+                        "Failed to restore `{}` -> `{}`: {}",
+                        group.name,
+                        target,
+                        e
+                    ),
+                }
+            }
+        }
+
+        if let Some(now) = now {
+            selected.push(PrfSelected {
+                name: Some(group.name.clone()),
+                now: Some(now),
+            });
+        }
+    }
+
+    if to_map(&selected) != saved_map {
+        save_selected(uid, selected)?;
+    }
+    Ok(())
+}
+
+/// 在后台恢复代理组选择，用于重新加载配置或重启内核之后
+pub fn restore_proxy_selections_later() {
+    AsyncHandler::spawn(|| async {
+        logging_error!(Type::Core, true, restore_proxy_selections().await);
+    });
+}
+
+/// 切换代理组节点并记录到当前订阅
+pub async fn select_proxy(group: String, name: String) -> Result<()> {
+    MihomoManager::global().select_proxy(&group, &name).await?;
+
+    let Some((uid, mut selected)) = current_selected() else {
+        return Ok(());
+    };
+    match selected
+        .iter_mut()
+        .find(|s| s.name.as_deref() == Some(group.as_str()))
+    {
+        Some(entry) if entry.now.as_deref() == Some(name.as_str()) => return Ok(()),
+        Some(entry) => entry.now = Some(name),
+        None => selected.push(PrfSelected {
+            name: Some(group),
+            now: Some(name),
+        }),
+    }
+    save_selected(uid, selected)
+}
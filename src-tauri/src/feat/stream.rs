@@ -0,0 +1,44 @@
+use crate::{core::handle, module::mihomo::MihomoManager, process::AsyncHandler};
+use anyhow::{bail, Result};
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use tauri::async_runtime::JoinHandle;
+
+/// 允许前端经由后端订阅的推送端点
+const STREAM_PATHS: [&str; 4] = ["/traffic", "/memory", "/connections", "/logs"];
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+static SUBSCRIPTIONS: Lazy<Mutex<HashMap<u32, JoinHandle<()>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 订阅控制器的推送，每条消息通过 `verge://clash-stream` 发给前端，返回订阅 id
+///
+/// 用于 TCP 控制器关闭、只开放 unix socket 的场景
+pub fn subscribe_clash_stream(path: String) -> Result<u32> {
+    let endpoint = path.split('?').next().unwrap_or_default();
+    if !STREAM_PATHS.contains(&endpoint) {
+        bail!("unsupported stream path: {path}");
+    }
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let task = AsyncHandler::spawn(move || async move {
+        let mut stream = Box::pin(MihomoManager::global().raw_stream(&path));
+        while let Some(data) = stream.next().await {
+            handle::Handle::notify_clash_stream(id, data);
+        }
+    });
+    SUBSCRIPTIONS.lock().insert(id, task);
+    Ok(id)
+}
+
+/// 取消订阅，id 不存在时忽略
+pub fn unsubscribe_clash_stream(id: u32) {
+    if let Some(task) = SUBSCRIPTIONS.lock().remove(&id) {
+        task.abort();
+    }
+}
@@ -82,6 +82,12 @@ impl AppHandleManager {
 
 #[allow(clippy::panic)]
 pub fn run() {
+    // 作为脚本子进程启动时只执行脚本，不初始化应用
+    if std::env::args().nth(1).as_deref() == Some(enhance::SCRIPT_WORKER_ARG) {
+        enhance::run_script_worker();
+        return;
+    }
+
     // Capture early deep link before any async setup (cold start on macOS)
     utils::resolve::capture_early_deep_link_from_args();
 
@@ -296,6 +302,10 @@ pub fn run() {
             cmd::get_runtime_yaml,
             cmd::get_runtime_exists,
             cmd::get_runtime_logs,
+            cmd::get_runtime_trace,
+            cmd::get_runtime_lint,
+            cmd::match_runtime_rule,
+            cmd::get_enhance_pipeline,
             cmd::invoke_uwp_tool,
             cmd::copy_clash_env,
             cmd::get_proxies,
@@ -325,6 +335,11 @@ pub fn run() {
             cmd::create_profile,
             cmd::import_profile,
             cmd::reorder_profile,
+            cmd::get_global_chain,
+            cmd::patch_global_chain,
+            cmd::patch_profile_chain,
+            cmd::get_chain_users,
+            cmd::import_rule_list,
             cmd::update_profile,
             cmd::delete_profile,
             cmd::read_profile_file,
@@ -332,11 +347,20 @@ pub fn run() {
             cmd::get_next_update_time,
             cmd::update_profiles_on_startup,
             cmd::create_profile_from_share_link,
+            cmd::export_share_links,
+            cmd::export_profile_subscription,
             // script validation
             cmd::script_validate_notice,
             cmd::validate_script_file,
             // clash api
             cmd::clash_api_get_proxy_delay,
+            cmd::clash_api_select_proxy,
+            cmd::clash_api_request,
+            cmd::clash_api_subscribe,
+            cmd::clash_api_unsubscribe,
+            cmd::clash_api_batch_test_delay,
+            cmd::get_latency_stats,
+            cmd::clear_latency_history,
             // backup
             cmd::create_webdav_backup,
             cmd::save_webdav_config,
@@ -0,0 +1,178 @@
+use crate::utils::{dirs, help};
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 每个节点保留的历史记录条数
+const MAX_SAMPLES: usize = 20;
+
+/// 单次测试结果，`delay` 为空表示超时或失败
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencySample {
+    pub time: i64,
+    pub delay: Option<u32>,
+}
+
+/// 由历史记录计算出的统计数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub name: String,
+    pub last: Option<u32>,
+    /// 成功样本的中位数
+    pub median: Option<u32>,
+    /// 相邻两次成功样本差值的平均值
+    pub jitter: Option<u32>,
+    /// 失败次数占比，0 ~ 1
+    pub loss: f64,
+    pub samples: usize,
+}
+
+impl LatencyStats {
+    pub fn from_samples(name: &str, samples: &[LatencySample]) -> Self {
+        let delays: Vec<u32> = samples.iter().filter_map(|s| s.delay).collect();
+
+        let median = if delays.is_empty() {
+            None
+        } else {
+            let mut sorted = delays.clone();
+            sorted.sort_unstable();
+            let mid = sorted.len() / 2;
+            if sorted.len().is_multiple_of(2) {
+                Some((sorted[mid - 1] + sorted[mid]) / 2)
+            } else {
+                Some(sorted[mid])
+            }
+        };
+
+        let jitter = if delays.len() < 2 {
+            None
+        } else {
+            let total: u64 = delays.windows(2).map(|w| w[0].abs_diff(w[1]) as u64).sum();
+            Some((total / (delays.len() as u64 - 1)) as u32)
+        };
+
+        let loss = if samples.is_empty() {
+            0.0
+        } else {
+            (samples.len() - delays.len()) as f64 / samples.len() as f64
+        };
+
+        Self {
+            name: name.into(),
+            last: samples.last().and_then(|s| s.delay),
+            median,
+            jitter,
+            loss,
+            samples: samples.len(),
+        }
+    }
+}
+
+/// 节点延迟历史，按节点名保存在 `latency_history.yaml`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LatencyHistory {
+    #[serde(default)]
+    pub nodes: HashMap<String, Vec<LatencySample>>,
+}
+
+impl LatencyHistory {
+    pub fn global() -> &'static Mutex<LatencyHistory> {
+        static HISTORY: OnceCell<Mutex<LatencyHistory>> = OnceCell::new();
+        HISTORY.get_or_init(|| {
+            let history = dirs::latency_history_path()
+                .and_then(|path| help::read_yaml::<LatencyHistory>(&path))
+                .unwrap_or_default();
+            Mutex::new(history)
+        })
+    }
+
+    pub fn record(&mut self, name: &str, delay: Option<u32>) {
+        let samples = self.nodes.entry(name.into()).or_default();
+        samples.push(LatencySample {
+            time: chrono::Local::now().timestamp(),
+            delay,
+        });
+        if samples.len() > MAX_SAMPLES {
+            let overflow = samples.len() - MAX_SAMPLES;
+            samples.drain(..overflow);
+        }
+    }
+
+    pub fn stats(&self, name: &str) -> LatencyStats {
+        let samples = self.nodes.get(name).map(Vec::as_slice).unwrap_or_default();
+        LatencyStats::from_samples(name, samples)
+    }
+
+    /// 按 `median` / `jitter` / `loss` 排序，没有成功样本的节点排在最后
+    pub fn sorted_stats(&self, sort_by: &str) -> Vec<LatencyStats> {
+        let mut list: Vec<LatencyStats> = self
+            .nodes
+            .iter()
+            .map(|(name, samples)| LatencyStats::from_samples(name, samples))
+            .collect();
+
+        let key = |stats: &LatencyStats| -> (bool, u64) {
+            let value = match sort_by {
+                "jitter" => stats.jitter.map(u64::from),
+                "loss" => Some((stats.loss * 1000.0) as u64),
+                _ => stats.median.map(u64::from),
+            };
+            (value.is_none(), value.unwrap_or_default())
+        };
+        list.sort_by(|a, b| key(a).cmp(&key(b)).then_with(|| a.name.cmp(&b.name)));
+        list
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    pub fn save(&self) -> Result<()> {
+        help::save_yaml(
+            &dirs::latency_history_path()?,
+            self,
+            Some("# Proxy latency history"),
+        )
+    }
+}
+
+#[test]
+fn test_latency_stats() {
+    let samples: Vec<LatencySample> = [Some(100), None, Some(300), Some(200), Some(120)]
+        .into_iter()
+        .map(|delay| LatencySample { time: 0, delay })
+        .collect();
+
+    let stats = LatencyStats::from_samples("node", &samples);
+    assert_eq!(stats.last, Some(120));
+    assert_eq!(stats.median, Some(160));
+    // |100-300| + |300-200| + |200-120| = 380
+    assert_eq!(stats.jitter, Some(126));
+    assert_eq!(stats.loss, 0.2);
+    assert_eq!(stats.samples, 5);
+
+    let empty = LatencyStats::from_samples("empty", &[]);
+    assert_eq!(empty.median, None);
+    assert_eq!(empty.jitter, None);
+}
+
+#[test]
+fn test_latency_history_sort() {
+    let mut history = LatencyHistory::default();
+    for _ in 0..MAX_SAMPLES + 5 {
+        history.record("fast", Some(50));
+    }
+    history.record("slow", Some(500));
+    history.record("dead", None);
+
+    assert_eq!(history.nodes["fast"].len(), MAX_SAMPLES);
+
+    let names: Vec<String> = history
+        .sorted_stats("median")
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    assert_eq!(names, vec!["fast", "slow", "dead"]);
+}
@@ -23,6 +23,8 @@ struct MihomoCache {
 pub struct MihomoManager {
     mihomo_cache: RwLock<Option<MihomoCache>>,
     create_lock: Mutex<()>,
+    // 缓存过期重建实例时沿用同一个熔断器
+    breaker: mihomo_api::CircuitBreaker,
 }
 
 impl MihomoManager {
@@ -30,10 +32,16 @@ impl MihomoManager {
         static INSTANCE: Lazy<MihomoManager> = Lazy::new(|| MihomoManager {
             mihomo_cache: RwLock::new(None),
             create_lock: Mutex::new(()),
+            breaker: mihomo_api::CircuitBreaker::new(),
         });
         &INSTANCE
     }
 
+    /// 内核启动后调用，避免熔断期内的请求被直接拒绝
+    pub fn reset_circuit_breaker() {
+        MihomoManager::__global().breaker.reset();
+    }
+
     pub fn global() -> mihomo_api::MihomoManager {
         let instance = MihomoManager::__global();
 
@@ -74,7 +82,8 @@ impl MihomoManager {
         // 创建新实例
         let (current_server, headers) = MihomoManager::get_clash_client_info()
             .unwrap_or_else(|| (String::new(), HeaderMap::new()));
-        let manager = mihomo_api::MihomoManager::new(current_server.clone(), headers);
+        let manager = MihomoManager::create_manager(current_server.clone(), headers)
+            .with_circuit_breaker(instance.breaker.clone());
 
         // 更新缓存
         {
@@ -91,9 +100,13 @@ impl MihomoManager {
 }
 
 impl MihomoManager {
+    /// 配置了 `external-controller-unix` 时优先走 socket，server 形如 `unix:///path/to/sock`
     pub fn get_clash_client_info() -> Option<(String, HeaderMap)> {
         let client = { Config::clash().data().get_client_info() };
-        let server = format!("http://{}", client.server);
+        let server = match client.unix_socket {
+            Some(path) => format!("unix://{path}"),
+            None => format!("http://{}", client.server),
+        };
         let mut headers = HeaderMap::new();
         headers.insert("Content-Type", "application/json".parse().unwrap());
         if let Some(secret) = client.secret {
@@ -104,5 +117,13 @@ impl MihomoManager {
         Some((server, headers))
     }
 
+    fn create_manager(server: String, headers: HeaderMap) -> mihomo_api::MihomoManager {
+        #[cfg(unix)]
+        if let Some(path) = server.strip_prefix("unix://") {
+            return mihomo_api::MihomoManager::new_unix(path.into(), headers);
+        }
+        mihomo_api::MihomoManager::new(server, headers)
+    }
+
     // 已移除未使用的 get_clash_client_info_or_default 和 get_traffic_ws_url 方法
 }
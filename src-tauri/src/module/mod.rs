@@ -1,3 +1,4 @@
+pub mod latency;
 pub mod lightweight;
 pub mod mihomo;
 pub mod sysinfo;
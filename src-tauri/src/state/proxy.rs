@@ -1,10 +1,10 @@
-use serde_json::Value;
+use mihomo_api::{Proxies, ProxyProviders};
 
 pub struct CmdProxyState {
     pub last_refresh_time: std::time::Instant,
     pub need_refresh: bool,
-    pub proxies: Box<Value>,
-    pub providers_proxies: Box<Value>,
+    pub proxies: Box<Proxies>,
+    pub providers_proxies: Box<ProxyProviders>,
 }
 
 impl Default for CmdProxyState {
@@ -12,8 +12,8 @@ impl Default for CmdProxyState {
         Self {
             last_refresh_time: std::time::Instant::now(),
             need_refresh: true,
-            proxies: Box::new(Value::Null),
-            providers_proxies: Box::new(Value::Null),
+            proxies: Box::default(),
+            providers_proxies: Box::default(),
         }
     }
 }
@@ -0,0 +1,510 @@
+//! sing-box 配置和 SIP008 订阅这两种 JSON 格式
+
+use super::{
+    share_link::{
+        insert_list, insert_map, insert_ss_plugin, insert_str, insert_transport, insert_true,
+        Transport,
+    },
+    SkippedLine,
+};
+use serde_json::Value as JsonValue;
+use serde_yaml::{Mapping, Value};
+use std::net::IpAddr;
+
+/// 不是代理的出站，mihomo 中有对应的内置策略
+const BUILTIN_OUTBOUNDS: [&str; 3] = ["direct", "block", "dns"];
+
+#[derive(Clone, Copy)]
+struct Json<'a>(&'a JsonValue);
+
+impl<'a> Json<'a> {
+    fn str(self, key: &str) -> Option<&'a str> {
+        self.0.get(key)?.as_str().filter(|value| !value.is_empty())
+    }
+
+    fn u64(self, key: &str) -> Option<u64> {
+        self.0.get(key)?.as_u64()
+    }
+
+    fn bool(self, key: &str) -> bool {
+        self.0
+            .get(key)
+            .and_then(JsonValue::as_bool)
+            .unwrap_or(false)
+    }
+
+    fn obj(self, key: &str) -> Option<Json<'a>> {
+        self.0.get(key).filter(|value| value.is_object()).map(Json)
+    }
+
+    fn array(self, key: &str) -> &'a [JsonValue] {
+        self.0
+            .get(key)
+            .and_then(JsonValue::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// 字符串数组用逗号连接，单个字符串原样返回
+    fn list(self, key: &str) -> Option<String> {
+        match self.0.get(key)? {
+            JsonValue::String(value) => Some(value.clone()),
+            JsonValue::Array(list) => {
+                let list = list
+                    .iter()
+                    .filter_map(JsonValue::as_str)
+                    .collect::<Vec<_>>();
+                (!list.is_empty()).then(|| list.join(","))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 转换 sing-box 配置中的 outbounds 和 endpoints，direct、block、dns 直接忽略
+pub fn convert_sing_box(config: &JsonValue) -> (Vec<Mapping>, Vec<SkippedLine>) {
+    let config = Json(config);
+    let outbounds = config
+        .array("outbounds")
+        .iter()
+        .chain(config.array("endpoints"));
+
+    let mut proxies = vec![];
+    let mut skipped = vec![];
+    for (index, outbound) in outbounds.enumerate() {
+        let kind = Json(outbound).str("type").unwrap_or_default();
+        if BUILTIN_OUTBOUNDS.contains(&kind) {
+            continue;
+        }
+        match convert_outbound(Json(outbound)) {
+            Ok(proxy) => proxies.push(proxy),
+            Err(reason) => skipped.push(SkippedLine {
+                line: index + 1,
+                text: format!("{} ({kind})", Json(outbound).str("tag").unwrap_or_default()),
+                reason,
+            }),
+        }
+    }
+    (proxies, skipped)
+}
+
+/// 转换 SIP008 订阅中的 servers
+pub fn convert_sip008(config: &JsonValue) -> (Vec<Mapping>, Vec<SkippedLine>) {
+    let mut proxies = vec![];
+    let mut skipped = vec![];
+    for (index, server) in Json(config).array("servers").iter().enumerate() {
+        match convert_sip008_server(Json(server)) {
+            Ok(proxy) => proxies.push(proxy),
+            Err(reason) => skipped.push(SkippedLine {
+                line: index + 1,
+                text: Json(server)
+                    .str("remarks")
+                    .or(Json(server).str("id"))
+                    .unwrap_or_default()
+                    .into(),
+                reason,
+            }),
+        }
+    }
+    (proxies, skipped)
+}
+
+fn new_proxy(
+    outbound: Json,
+    kind: &str,
+    server_key: &str,
+    port_key: &str,
+) -> Result<Mapping, String> {
+    let server = outbound.str(server_key).ok_or("missing server")?;
+    let port = outbound
+        .u64(port_key)
+        .filter(|port| *port <= u16::MAX as u64)
+        .ok_or("missing server port")?;
+    let name = outbound
+        .str("tag")
+        .or(outbound.str("remarks"))
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{server}:{port}"));
+
+    let mut proxy = Mapping::new();
+    proxy.insert("name".into(), name.into());
+    proxy.insert("type".into(), kind.into());
+    proxy.insert("server".into(), server.into());
+    proxy.insert("port".into(), port.into());
+    Ok(proxy)
+}
+
+fn convert_sip008_server(server: Json) -> Result<Mapping, String> {
+    let mut proxy = new_proxy(server, "ss", "server", "server_port")?;
+    proxy.insert(
+        "cipher".into(),
+        server.str("method").ok_or("missing method")?.into(),
+    );
+    proxy.insert(
+        "password".into(),
+        server.str("password").ok_or("missing password")?.into(),
+    );
+    proxy.insert("udp".into(), true.into());
+    if let Some(plugin) = server.str("plugin") {
+        insert_plugin(&mut proxy, plugin, server.str("plugin_opts"))?;
+    }
+    Ok(proxy)
+}
+
+fn insert_plugin(proxy: &mut Mapping, plugin: &str, opts: Option<&str>) -> Result<(), String> {
+    match opts {
+        Some(opts) => insert_ss_plugin(proxy, &format!("{plugin};{opts}")),
+        None => insert_ss_plugin(proxy, plugin),
+    }
+}
+
+fn convert_outbound(outbound: Json) -> Result<Mapping, String> {
+    let kind = outbound.str("type").unwrap_or_default();
+    let mut proxy = match kind {
+        "selector" | "urltest" => {
+            return Err("group outbounds are replaced by the default group".into())
+        }
+        "wireguard" if outbound.0.get("peers").is_some() => return convert_endpoint(outbound),
+        "vless" | "vmess" | "trojan" | "hysteria2" | "tuic" | "wireguard" => {
+            new_proxy(outbound, kind, "server", "server_port")?
+        }
+        "shadowsocks" => new_proxy(outbound, "ss", "server", "server_port")?,
+        kind => return Err(format!("unsupported outbound type `{kind}`")),
+    };
+
+    match kind {
+        "vless" => {
+            let uuid = outbound.str("uuid").ok_or("missing uuid")?;
+            proxy.insert("uuid".into(), uuid.into());
+            proxy.insert("udp".into(), true.into());
+            insert_str(&mut proxy, "flow", outbound.str("flow"));
+            insert_str(
+                &mut proxy,
+                "packet-encoding",
+                outbound.str("packet_encoding"),
+            );
+            insert_tls(&mut proxy, outbound, "servername");
+            insert_outbound_transport(&mut proxy, outbound)?;
+        }
+        "vmess" => {
+            let uuid = outbound.str("uuid").ok_or("missing uuid")?;
+            proxy.insert("uuid".into(), uuid.into());
+            proxy.insert(
+                "alterId".into(),
+                outbound.u64("alter_id").unwrap_or(0).into(),
+            );
+            let cipher = outbound.str("security").unwrap_or("auto");
+            proxy.insert("cipher".into(), cipher.into());
+            proxy.insert("udp".into(), true.into());
+            insert_str(
+                &mut proxy,
+                "packet-encoding",
+                outbound.str("packet_encoding"),
+            );
+            insert_tls(&mut proxy, outbound, "servername");
+            insert_outbound_transport(&mut proxy, outbound)?;
+        }
+        "trojan" => {
+            let password = outbound.str("password").ok_or("missing password")?;
+            proxy.insert("password".into(), password.into());
+            proxy.insert("udp".into(), true.into());
+            insert_tls(&mut proxy, outbound, "sni");
+            insert_outbound_transport(&mut proxy, outbound)?;
+        }
+        "shadowsocks" => {
+            let cipher = outbound.str("method").ok_or("missing method")?;
+            let password = outbound.str("password").ok_or("missing password")?;
+            proxy.insert("cipher".into(), cipher.into());
+            proxy.insert("password".into(), password.into());
+            proxy.insert("udp".into(), true.into());
+            // udp_over_tcp 可以是布尔值或 `{ "enabled": true }`
+            let uot = outbound.bool("udp_over_tcp")
+                || outbound
+                    .obj("udp_over_tcp")
+                    .is_some_and(|uot| uot.bool("enabled"));
+            insert_true(&mut proxy, "udp-over-tcp", uot);
+            if let Some(plugin) = outbound.str("plugin") {
+                insert_plugin(&mut proxy, plugin, outbound.str("plugin_opts"))?;
+            }
+        }
+        "hysteria2" => {
+            insert_str(&mut proxy, "password", outbound.str("password"));
+            let ports = outbound
+                .array("server_ports")
+                .iter()
+                .filter_map(JsonValue::as_str)
+                .map(|range| range.replace(':', "-"))
+                .collect::<Vec<_>>();
+            if !ports.is_empty() {
+                proxy.insert("ports".into(), ports.join(",").into());
+            }
+            if let Some(up) = outbound.u64("up_mbps") {
+                proxy.insert("up".into(), up.into());
+            }
+            if let Some(down) = outbound.u64("down_mbps") {
+                proxy.insert("down".into(), down.into());
+            }
+            if let Some(obfs) = outbound.obj("obfs") {
+                insert_str(&mut proxy, "obfs", obfs.str("type"));
+                insert_str(&mut proxy, "obfs-password", obfs.str("password"));
+            }
+            insert_tls(&mut proxy, outbound, "sni");
+        }
+        "tuic" => {
+            let uuid = outbound.str("uuid").ok_or("missing uuid")?;
+            proxy.insert("uuid".into(), uuid.into());
+            insert_str(&mut proxy, "password", outbound.str("password"));
+            insert_str(
+                &mut proxy,
+                "congestion-controller",
+                outbound.str("congestion_control"),
+            );
+            insert_str(&mut proxy, "udp-relay-mode", outbound.str("udp_relay_mode"));
+            insert_true(
+                &mut proxy,
+                "reduce-rtt",
+                outbound.bool("zero_rtt_handshake"),
+            );
+            insert_tls(&mut proxy, outbound, "sni");
+            proxy.insert("udp".into(), true.into());
+        }
+        "wireguard" => {
+            let private_key = outbound.str("private_key").ok_or("missing private key")?;
+            let public_key = outbound
+                .str("peer_public_key")
+                .ok_or("missing public key")?;
+            proxy.insert("private-key".into(), private_key.into());
+            proxy.insert("public-key".into(), public_key.into());
+            insert_str(&mut proxy, "pre-shared-key", outbound.str("pre_shared_key"));
+            insert_wireguard(
+                &mut proxy,
+                outbound,
+                outbound.array("local_address"),
+                outbound,
+            );
+        }
+        _ => unreachable!(),
+    }
+    Ok(proxy)
+}
+
+/// sing-box 1.11 起 wireguard 改为 endpoint，服务器信息写在 peers 中
+fn convert_endpoint(endpoint: Json) -> Result<Mapping, String> {
+    let peers = endpoint.array("peers");
+    let peer = match peers {
+        [peer] => Json(peer),
+        [] => return Err("missing peers".into()),
+        _ => return Err("multiple peers are not supported".into()),
+    };
+    let mut proxy = new_proxy(peer, "wireguard", "address", "port")?;
+    if let Some(tag) = endpoint.str("tag") {
+        proxy.insert("name".into(), tag.into());
+    }
+    let private_key = endpoint.str("private_key").ok_or("missing private key")?;
+    let public_key = peer.str("public_key").ok_or("missing public key")?;
+    proxy.insert("private-key".into(), private_key.into());
+    proxy.insert("public-key".into(), public_key.into());
+    insert_str(&mut proxy, "pre-shared-key", peer.str("pre_shared_key"));
+    insert_list(
+        &mut proxy,
+        "allowed-ips",
+        peer.list("allowed_ips").as_deref(),
+    );
+    insert_wireguard(&mut proxy, endpoint, endpoint.array("address"), peer);
+    Ok(proxy)
+}
+
+fn insert_wireguard(proxy: &mut Mapping, outbound: Json, address: &[JsonValue], peer: Json) {
+    for address in address.iter().filter_map(JsonValue::as_str) {
+        let ip = address.split('/').next().unwrap_or_default();
+        match ip.parse::<IpAddr>() {
+            Ok(IpAddr::V4(_)) => proxy.insert("ip".into(), ip.into()),
+            Ok(IpAddr::V6(_)) => proxy.insert("ipv6".into(), ip.into()),
+            Err(_) => continue,
+        };
+    }
+    let reserved = peer
+        .array("reserved")
+        .iter()
+        .filter_map(JsonValue::as_u64)
+        .map(Value::from)
+        .collect::<Vec<_>>();
+    if !reserved.is_empty() {
+        proxy.insert("reserved".into(), Value::Sequence(reserved));
+    }
+    if let Some(mtu) = outbound.u64("mtu") {
+        proxy.insert("mtu".into(), mtu.into());
+    }
+    proxy.insert("udp".into(), true.into());
+}
+
+/// tls 对象，vless / vmess 写入 tls 字段，其他协议本身就使用 TLS
+fn insert_tls(proxy: &mut Mapping, outbound: Json, sni_key: &str) {
+    let Some(tls) = outbound.obj("tls").filter(|tls| tls.bool("enabled")) else {
+        return;
+    };
+    if sni_key == "servername" {
+        proxy.insert("tls".into(), true.into());
+    }
+    insert_str(proxy, sni_key, tls.str("server_name"));
+    insert_true(proxy, "skip-cert-verify", tls.bool("insecure"));
+    insert_true(proxy, "disable-sni", tls.bool("disable_sni"));
+    insert_list(proxy, "alpn", tls.list("alpn").as_deref());
+    if let Some(utls) = tls.obj("utls").filter(|utls| utls.bool("enabled")) {
+        let fingerprint = utls.str("fingerprint").unwrap_or("chrome");
+        proxy.insert("client-fingerprint".into(), fingerprint.into());
+    }
+    if let Some(reality) = tls.obj("reality").filter(|reality| reality.bool("enabled")) {
+        let mut opts = Mapping::new();
+        insert_str(&mut opts, "public-key", reality.str("public_key"));
+        insert_str(&mut opts, "short-id", reality.str("short_id"));
+        insert_map(proxy, "reality-opts", opts);
+    }
+}
+
+/// transport 对象，http 传输在 TLS 下为 HTTP/2
+fn insert_outbound_transport(proxy: &mut Mapping, outbound: Json) -> Result<(), String> {
+    let Some(transport) = outbound.obj("transport") else {
+        return Ok(());
+    };
+    let tls = outbound.obj("tls").is_some_and(|tls| tls.bool("enabled"));
+    let network = match transport.str("type").unwrap_or_default() {
+        "http" if tls => "h2",
+        "quic" => return Err("quic transport is not supported".into()),
+        network => network,
+    };
+    let host = match network {
+        "ws" => transport
+            .obj("headers")
+            .and_then(|headers| headers.str("Host"))
+            .map(str::to_string),
+        _ => transport.list("host"),
+    };
+    let early_data = transport.u64("max_early_data").map(|ed| ed.to_string());
+    insert_transport(
+        proxy,
+        Transport {
+            network,
+            path: transport.str("path"),
+            host: host.as_deref(),
+            service_name: transport.str("service_name"),
+            early_data: early_data.as_deref(),
+            ..Transport::default()
+        },
+    )
+}
+
+#[test]
+fn test_convert_sing_box() {
+    let config = serde_json::json!({
+        "outbounds": [
+            { "type": "selector", "tag": "proxy", "outbounds": ["vless-out"] },
+            {
+                "type": "vless", "tag": "vless-out", "server": "a.example.com", "server_port": 443,
+                "uuid": "id", "flow": "xtls-rprx-vision",
+                "tls": {
+                    "enabled": true, "server_name": "www.microsoft.com",
+                    "utls": { "enabled": true, "fingerprint": "chrome" },
+                    "reality": { "enabled": true, "public_key": "KEY", "short_id": "ab" }
+                }
+            },
+            {
+                "type": "vmess", "tag": "vmess-out", "server": "v.example.com", "server_port": 443,
+                "uuid": "id", "security": "auto",
+                "tls": { "enabled": true, "server_name": "v.example.com" },
+                "transport": { "type": "ws", "path": "/ws", "headers": { "Host": "cdn.example.com" } }
+            },
+            {
+                "type": "trojan", "tag": "trojan-out", "server": "t.example.com", "server_port": 443,
+                "password": "pw", "tls": { "enabled": true, "server_name": "t.example.com", "insecure": true },
+                "transport": { "type": "grpc", "service_name": "svc" }
+            },
+            {
+                "type": "shadowsocks", "tag": "ss-out", "server": "1.2.3.4", "server_port": 8388,
+                "method": "aes-128-gcm", "password": "pw",
+                "plugin": "obfs-local", "plugin_opts": "obfs=http;obfs-host=bing.com"
+            },
+            {
+                "type": "hysteria2", "tag": "hy2-out", "server": "h.example.com", "server_port": 443,
+                "server_ports": ["20000:30000"], "password": "pw", "up_mbps": 50,
+                "obfs": { "type": "salamander", "password": "x" },
+                "tls": { "enabled": true, "server_name": "h.example.com", "alpn": ["h3"] }
+            },
+            {
+                "type": "tuic", "tag": "tuic-out", "server": "u.example.com", "server_port": 443,
+                "uuid": "id", "password": "pw", "congestion_control": "bbr",
+                "tls": { "enabled": true, "server_name": "u.example.com", "alpn": ["h3"] }
+            },
+            { "type": "direct", "tag": "direct" },
+            { "type": "shadowtls", "tag": "stls", "server": "s.example.com", "server_port": 443 }
+        ],
+        "endpoints": [{
+            "type": "wireguard", "tag": "wg-ep", "address": ["10.0.0.2/32", "fd00::2/128"],
+            "private_key": "priv", "mtu": 1408,
+            "peers": [{
+                "address": "w.example.com", "port": 51820, "public_key": "pub",
+                "allowed_ips": ["0.0.0.0/0"], "reserved": [1, 2, 3]
+            }]
+        }]
+    });
+
+    let (proxies, skipped) = convert_sing_box(&config);
+    let expected = serde_yaml::from_str::<Vec<Mapping>>(
+        r#"
+        - { name: vless-out, type: vless, server: a.example.com, port: 443, uuid: id, udp: true,
+            flow: xtls-rprx-vision, tls: true, servername: www.microsoft.com, client-fingerprint: chrome,
+            reality-opts: { public-key: KEY, short-id: ab } }
+        - { name: vmess-out, type: vmess, server: v.example.com, port: 443, uuid: id, alterId: 0,
+            cipher: auto, udp: true, tls: true, servername: v.example.com, network: ws,
+            ws-opts: { path: /ws, headers: { Host: cdn.example.com } } }
+        - { name: trojan-out, type: trojan, server: t.example.com, port: 443, password: pw, udp: true,
+            sni: t.example.com, skip-cert-verify: true, network: grpc, grpc-opts: { grpc-service-name: svc } }
+        - { name: ss-out, type: ss, server: 1.2.3.4, port: 8388, cipher: aes-128-gcm, password: pw,
+            udp: true, plugin: obfs, plugin-opts: { mode: http, host: bing.com } }
+        - { name: hy2-out, type: hysteria2, server: h.example.com, port: 443, password: pw,
+            ports: 20000-30000, up: 50, obfs: salamander, obfs-password: x, sni: h.example.com, alpn: [h3] }
+        - { name: tuic-out, type: tuic, server: u.example.com, port: 443, uuid: id, password: pw,
+            congestion-controller: bbr, sni: u.example.com, alpn: [h3], udp: true }
+        - { name: wg-ep, type: wireguard, server: w.example.com, port: 51820, private-key: priv,
+            public-key: pub, allowed-ips: [0.0.0.0/0], ip: 10.0.0.2, ipv6: "fd00::2",
+            reserved: [1, 2, 3], mtu: 1408, udp: true }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(proxies, expected);
+
+    let skipped = skipped
+        .iter()
+        .map(|skipped| (skipped.line, skipped.text.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        skipped,
+        vec![(1, "proxy (selector)"), (9, "stls (shadowtls)")]
+    );
+}
+
+#[test]
+fn test_convert_sip008() {
+    let config = serde_json::json!({
+        "version": 1,
+        "servers": [
+            {
+                "id": "27b8a625-4f4b-4428-9f0f-8a2317db7c79", "remarks": "Name of the server",
+                "server": "example.com", "server_port": 8388, "password": "example",
+                "method": "chacha20-ietf-poly1305", "plugin": "v2ray-plugin", "plugin_opts": "tls;host=cdn.com"
+            },
+            { "remarks": "broken", "server": "example.com", "server_port": 8388, "method": "aes-128-gcm" }
+        ]
+    });
+    let (proxies, skipped) = convert_sip008(&config);
+    let expected = serde_yaml::from_str::<Mapping>(
+        r#"{ name: Name of the server, type: ss, server: example.com, port: 8388,
+             cipher: chacha20-ietf-poly1305, password: example, udp: true, plugin: v2ray-plugin,
+             plugin-opts: { mode: websocket, tls: true, host: cdn.com } }"#,
+    )
+    .unwrap();
+    assert_eq!(proxies, vec![expected]);
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].text, "broken");
+    assert_eq!(skipped[0].reason, "missing password");
+}
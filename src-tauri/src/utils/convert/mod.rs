@@ -0,0 +1,50 @@
+//! 其他客户端的订阅、规则格式转换为 Clash 配置
+
+pub mod json;
+pub mod rules;
+pub mod share_link;
+pub mod uri_list;
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// 无法转换的一行，line 从 1 开始，JSON 订阅中为出站的序号
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedLine {
+    pub line: usize,
+    pub text: String,
+    pub reason: String,
+}
+
+/// 解码订阅中常见的 base64，兼容 URL-safe 字母表、缺省的填充和换行
+pub fn decode_base64(text: &str) -> Option<String> {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect();
+    let bytes = general_purpose::GeneralPurpose::new(
+        &base64::alphabet::STANDARD,
+        general_purpose::GeneralPurposeConfig::new()
+            .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent),
+    )
+    .decode(text.trim_end_matches('='))
+    .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// 名称已被使用时加上序号，如 `name 2`
+pub fn unique_name(names: &mut HashSet<String>, name: String) -> String {
+    let mut unique = name.clone();
+    let mut index = 2;
+    while !names.insert(unique.clone()) {
+        unique = format!("{name} {index}");
+        index += 1;
+    }
+    unique
+}
@@ -0,0 +1,269 @@
+use super::SkippedLine;
+use serde::Serialize;
+
+/// 转换结果，rules 为 Clash 规则
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RuleListImport {
+    pub rules: Vec<String>,
+    pub skipped: Vec<SkippedLine>,
+}
+
+/// 转换 Surge / Quantumult X / Loon 的规则列表
+///
+/// 指定 policy 时所有规则都使用该策略，否则使用每行自带的策略，没有策略的行会被跳过
+pub fn convert_rule_list(text: &str, policy: Option<&str>) -> RuleListImport {
+    let mut result = RuleListImport::default();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) || line.starts_with("//") {
+            continue;
+        }
+        // 去掉行尾注释
+        let line = line.split(" //").next().unwrap_or(line).trim();
+
+        match convert_rule(line, policy) {
+            Ok(rule) => result.rules.push(rule),
+            Err(reason) => result.skipped.push(SkippedLine {
+                line: index + 1,
+                text: line.into(),
+                reason,
+            }),
+        }
+    }
+    result
+}
+
+/// 转换一条带策略的规则
+fn convert_rule(line: &str, policy: Option<&str>) -> Result<String, String> {
+    let (kind, rest) = line.split_once(',').unwrap_or((line, ""));
+    let kind = kind.trim().to_uppercase();
+
+    if let Some(clash_kind) = logic_kind(&kind) {
+        let (payload, tail) = split_logic(rest).ok_or("unbalanced parentheses")?;
+        let operands = payload
+            .iter()
+            .map(|operand| convert_condition(operand))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|operand| format!("({operand})"))
+            .collect::<Vec<_>>()
+            .join(",");
+        let target = pick_policy(tail.first().copied(), policy)?;
+        return Ok(format!("{clash_kind},({operands}),{target}"));
+    }
+
+    let parts = rest.split(',').map(str::trim).collect::<Vec<_>>();
+    if kind == "FINAL" || kind == "MATCH" {
+        let target = pick_policy(parts.first().copied().filter(|p| !p.is_empty()), policy)?;
+        return Ok(format!("MATCH,{target}"));
+    }
+
+    let clash_kind = rule_kind(&kind)?;
+    let payload = parts
+        .first()
+        .filter(|payload| !payload.is_empty())
+        .ok_or("missing payload")?;
+    let (own_policy, no_resolve) = split_options(&parts[1..]);
+    let target = pick_policy(own_policy, policy)?;
+    let suffix = match no_resolve && is_ip_kind(clash_kind) {
+        true => ",no-resolve",
+        false => "",
+    };
+    Ok(format!("{clash_kind},{payload},{target}{suffix}"))
+}
+
+/// 转换逻辑规则中不带策略的条件
+fn convert_condition(condition: &str) -> Result<String, String> {
+    let (kind, rest) = condition.split_once(',').ok_or("missing payload")?;
+    let kind = kind.trim().to_uppercase();
+
+    if let Some(clash_kind) = logic_kind(&kind) {
+        let (payload, _) = split_logic(rest).ok_or("unbalanced parentheses")?;
+        let operands = payload
+            .iter()
+            .map(|operand| convert_condition(operand).map(|operand| format!("({operand})")))
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(format!("{clash_kind},({})", operands.join(",")));
+    }
+
+    let clash_kind = rule_kind(&kind)?;
+    let mut parts = rest.split(',').map(str::trim);
+    let payload = parts
+        .next()
+        .filter(|p| !p.is_empty())
+        .ok_or("missing payload")?;
+    let no_resolve = parts.any(|part| part.eq_ignore_ascii_case("no-resolve"));
+    let suffix = match no_resolve && is_ip_kind(clash_kind) {
+        true => ",no-resolve",
+        false => "",
+    };
+    Ok(format!("{clash_kind},{payload}{suffix}"))
+}
+
+/// 各客户端的规则类型对应的 Clash 类型，QX 使用小写和 host 前缀
+fn rule_kind(kind: &str) -> Result<&'static str, String> {
+    let clash_kind = match kind {
+        "DOMAIN" | "HOST" => "DOMAIN",
+        "DOMAIN-SUFFIX" | "HOST-SUFFIX" => "DOMAIN-SUFFIX",
+        "DOMAIN-KEYWORD" | "HOST-KEYWORD" => "DOMAIN-KEYWORD",
+        "DOMAIN-WILDCARD" | "HOST-WILDCARD" => "DOMAIN-WILDCARD",
+        "DOMAIN-REGEX" | "HOST-REGEX" => "DOMAIN-REGEX",
+        "IP-CIDR" => "IP-CIDR",
+        "IP6-CIDR" | "IP-CIDR6" => "IP-CIDR6",
+        "IP-ASN" => "IP-ASN",
+        "GEOIP" => "GEOIP",
+        "SRC-IP" | "SRC-IP-CIDR" => "SRC-IP-CIDR",
+        "DEST-PORT" | "DST-PORT" => "DST-PORT",
+        "SRC-PORT" => "SRC-PORT",
+        "IN-PORT" => "IN-PORT",
+        "PROCESS-NAME" => "PROCESS-NAME",
+        _ => return Err(format!("unsupported rule type `{kind}`")),
+    };
+    Ok(clash_kind)
+}
+
+fn logic_kind(kind: &str) -> Option<&'static str> {
+    match kind {
+        "AND" => Some("AND"),
+        "OR" => Some("OR"),
+        "NOT" => Some("NOT"),
+        _ => None,
+    }
+}
+
+fn is_ip_kind(kind: &str) -> bool {
+    matches!(kind, "IP-CIDR" | "IP-CIDR6" | "IP-ASN" | "GEOIP")
+}
+
+/// 规则后面的部分：第一个不是选项的为策略，其余选项中只保留 no-resolve
+fn split_options<'a>(parts: &[&'a str]) -> (Option<&'a str>, bool) {
+    let is_option = |part: &str| {
+        let part = part.to_lowercase();
+        part == "no-resolve"
+            || part == "extended-matching"
+            || part == "pre-matching"
+            || part.starts_with("notification")
+            || part.starts_with("force-remote-dns")
+    };
+    let policy = parts
+        .iter()
+        .copied()
+        .find(|part| !part.is_empty() && !is_option(part));
+    let no_resolve = parts
+        .iter()
+        .any(|part| part.eq_ignore_ascii_case("no-resolve"));
+    (policy, no_resolve)
+}
+
+/// 指定的策略优先，其次是行内策略，内置策略转换为 Clash 的写法
+fn pick_policy(own: Option<&str>, policy: Option<&str>) -> Result<String, String> {
+    if let Some(policy) = policy.filter(|policy| !policy.is_empty()) {
+        return Ok(policy.into());
+    }
+    let own = own.ok_or("missing policy")?;
+    let upper = own.to_uppercase();
+    Ok(match upper.as_str() {
+        "DIRECT" => "DIRECT".into(),
+        "REJECT-DROP" => "REJECT-DROP".into(),
+        _ if upper.starts_with("REJECT") => "REJECT".into(),
+        _ => own.into(),
+    })
+}
+
+/// 拆开 `((A,a),(B,b)),policy`，返回去掉括号的条件和后面的部分
+fn split_logic(rest: &str) -> Option<(Vec<&str>, Vec<&str>)> {
+    let rest = rest.trim();
+    let mut depth = 0usize;
+    let mut end = None;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    end = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let end = end?;
+    let inner = rest.strip_prefix('(')?[..end - 1].trim();
+    let tail = rest[end + 1..]
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+
+    let mut operands = vec![];
+    let mut depth = 0usize;
+    let mut start = None;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => {
+                if depth == 0 {
+                    start = Some(i + 1);
+                }
+                depth += 1;
+            }
+            ')' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    operands.push(inner[start?..i].trim());
+                }
+            }
+            _ => {}
+        }
+    }
+    (depth == 0 && !operands.is_empty()).then_some((operands, tail))
+}
+
+#[test]
+fn test_convert_rule_list() {
+    let text = r#"
+# Surge
+DOMAIN-SUFFIX,google.com
+HOST-SUFFIX, youtube.com, proxy
+host-keyword, ads, reject-200
+IP6-CIDR,2001:db8::/32,no-resolve
+IP-CIDR,10.0.0.0/8,DIRECT,no-resolve
+DOMAIN,example.com,Proxy,extended-matching
+USER-AGENT,Instagram*
+AND,((DOMAIN-SUFFIX,a.com),(DEST-PORT,443)),REJECT-TINYGIF
+URL-REGEX,^https?://ad\.
+AND,((USER-AGENT,x),(DOMAIN,b.com))
+FINAL,Proxy
+"#;
+    let result = convert_rule_list(text, None);
+    assert_eq!(
+        result.rules,
+        vec![
+            "DOMAIN-SUFFIX,youtube.com,proxy",
+            "DOMAIN-KEYWORD,ads,REJECT",
+            "IP-CIDR,10.0.0.0/8,DIRECT,no-resolve",
+            "DOMAIN,example.com,Proxy",
+            "AND,((DOMAIN-SUFFIX,a.com),(DST-PORT,443)),REJECT",
+            "MATCH,Proxy",
+        ]
+    );
+    assert_eq!(
+        result
+            .skipped
+            .iter()
+            .map(|skipped| (skipped.line, skipped.reason.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            (3, "missing policy"),
+            (6, "missing policy"),
+            (9, "unsupported rule type `USER-AGENT`"),
+            (11, "unsupported rule type `URL-REGEX`"),
+            (12, "unsupported rule type `USER-AGENT`"),
+        ]
+    );
+
+    let result = convert_rule_list(text, Some("Ads"));
+    assert_eq!(result.rules[0], "DOMAIN-SUFFIX,google.com,Ads");
+    assert_eq!(result.rules[3], "IP-CIDR6,2001:db8::/32,Ads,no-resolve");
+    assert_eq!(result.skipped.len(), 3);
+}
@@ -0,0 +1,292 @@
+use super::{
+    decode_base64, json,
+    share_link::{export_share_link, parse_share_link, SharedLink},
+    unique_name, SkippedLine,
+};
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+
+/// 订阅内容的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionFormat {
+    /// 含有 `proxies` 或 `proxy-providers` 的 Clash 配置
+    Clash,
+    /// base64 编码的分享链接列表
+    Base64UriList,
+    /// 每行一个分享链接
+    UriList,
+    /// sing-box 配置中的 outbounds
+    SingBox,
+    /// Shadowsocks 的 SIP008 在线配置
+    Sip008,
+}
+
+/// 转换后的配置和无法转换的行
+#[derive(Debug, Clone)]
+pub struct SubscriptionConfig {
+    pub config: Mapping,
+    pub skipped: Vec<SkippedLine>,
+}
+
+/// 判断订阅内容的格式，无法识别时返回 None
+pub fn detect_format(data: &str) -> Option<SubscriptionFormat> {
+    if let Ok(yaml) = serde_yaml::from_str::<Mapping>(data) {
+        if yaml.contains_key("proxies") || yaml.contains_key("proxy-providers") {
+            return Some(SubscriptionFormat::Clash);
+        }
+    }
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
+        let is_array = |key: &str| json.get(key).is_some_and(serde_json::Value::is_array);
+        if is_array("outbounds") || is_array("endpoints") {
+            return Some(SubscriptionFormat::SingBox);
+        }
+        if is_array("servers") {
+            return Some(SubscriptionFormat::Sip008);
+        }
+    }
+    if is_uri_list(data) {
+        return Some(SubscriptionFormat::UriList);
+    }
+    decode_base64(data)
+        .filter(|decoded| is_uri_list(decoded))
+        .map(|_| SubscriptionFormat::Base64UriList)
+}
+
+fn is_uri_list(data: &str) -> bool {
+    data.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .any(|line| {
+            line.split_once("://").is_some_and(|(scheme, _)| {
+                !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric())
+            })
+        })
+}
+
+/// 将分享链接列表转换为代理，填入模板的 `proxies`
+///
+/// 模板中的代理组可以用 `include-all-proxies: true` 引用这些代理，重名的代理会加上序号
+pub fn convert_uri_list(data: &str, template: &str) -> Result<SubscriptionConfig> {
+    let decoded;
+    let data = match is_uri_list(data) {
+        true => data,
+        false => {
+            decoded = decode_base64(data).context("the subscription is not a uri list")?;
+            decoded.as_str()
+        }
+    };
+
+    let mut proxies = vec![];
+    let mut skipped = vec![];
+    for (index, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_share_link(line) {
+            Ok(proxy) => proxies.push(proxy),
+            Err(reason) => skipped.push(SkippedLine {
+                line: index + 1,
+                text: line.into(),
+                reason,
+            }),
+        }
+    }
+    fill_template(template, proxies, skipped)
+}
+
+/// 按识别出的格式把订阅转换为 Clash 配置，非 Clash 格式的代理填入模板
+pub fn convert_subscription(
+    data: &str,
+    format: SubscriptionFormat,
+    template: &str,
+) -> Result<SubscriptionConfig> {
+    let (proxies, skipped) = match format {
+        SubscriptionFormat::Clash => {
+            let config = serde_yaml::from_str(data)?;
+            return Ok(SubscriptionConfig {
+                config,
+                skipped: vec![],
+            });
+        }
+        SubscriptionFormat::UriList | SubscriptionFormat::Base64UriList => {
+            return convert_uri_list(data, template)
+        }
+        SubscriptionFormat::SingBox => json::convert_sing_box(&serde_json::from_str(data)?),
+        SubscriptionFormat::Sip008 => json::convert_sip008(&serde_json::from_str(data)?),
+    };
+    fill_template(template, proxies, skipped)
+}
+
+/// 代理写入模板的 `proxies`，重名的代理会加上序号
+fn fill_template(
+    template: &str,
+    proxies: Vec<Mapping>,
+    skipped: Vec<SkippedLine>,
+) -> Result<SubscriptionConfig> {
+    let mut config = serde_yaml::from_str::<Mapping>(template)
+        .context("the subscription template is invalid yaml")?;
+    if proxies.is_empty() {
+        bail!("the subscription does not contain any supported proxy");
+    }
+
+    let mut names = HashSet::new();
+    let proxies = proxies
+        .into_iter()
+        .map(|mut proxy| {
+            let name = proxy
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let name = unique_name(&mut names, name);
+            proxy.insert("name".into(), name.into());
+            Value::Mapping(proxy)
+        })
+        .collect();
+    config.insert("proxies".into(), Value::Sequence(proxies));
+    Ok(SubscriptionConfig { config, skipped })
+}
+
+/// 导出的订阅内容，links 为每个代理的导出结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UriListExport {
+    pub content: String,
+    pub links: Vec<SharedLink>,
+}
+
+/// 将代理导出为 base64 编码的分享链接订阅，无法导出的代理不写入订阅
+pub fn export_uri_list(proxies: &[Value]) -> UriListExport {
+    let links = proxies
+        .iter()
+        .filter_map(Value::as_mapping)
+        .map(export_share_link)
+        .collect::<Vec<_>>();
+    let list = links
+        .iter()
+        .filter_map(|link| link.link.as_deref())
+        .collect::<Vec<_>>()
+        .join("\n");
+    UriListExport {
+        content: STANDARD.encode(list),
+        links,
+    }
+}
+
+#[test]
+fn test_convert_uri_list() {
+    let vmess = STANDARD.encode(
+        r#"{"v":"2","ps":"vm","add":"v.example.com","port":"443","id":"b831381d-6324-4d53-ad4f-8cda48b30811","aid":"0","net":"ws","path":"/ws","host":"cdn.example.com","tls":"tls"}"#,
+    );
+    let list = [
+        "vless://b831381d-6324-4d53-ad4f-8cda48b30811@a.example.com:443?security=reality&pbk=KEY&sid=ab&sni=www.microsoft.com&fp=chrome&flow=xtls-rprx-vision#A%20node".to_string(),
+        format!("vmess://{vmess}"),
+        "ss://YWVzLTI1Ni1nY206cGFzcw@1.2.3.4:8388#A%20node".into(),
+        "trojan://secret@t.example.com:443?sni=t.example.com&type=grpc&serviceName=svc#tr".into(),
+        "hy2://pass@h.example.com:8443?sni=h.example.com&insecure=1&obfs=salamander&obfs-password=x#hy".into(),
+        "wireguard://unsupported@w.example.com:51820#wg".into(),
+    ]
+    .join("\n");
+
+    assert_eq!(detect_format(&list), Some(SubscriptionFormat::UriList));
+    let encoded = STANDARD.encode(&list);
+    assert_eq!(
+        detect_format(&encoded),
+        Some(SubscriptionFormat::Base64UriList)
+    );
+    assert_eq!(
+        detect_format("proxies: []\nrules: []"),
+        Some(SubscriptionFormat::Clash)
+    );
+    assert_eq!(
+        detect_format(r#"{"outbounds": [{"type": "direct"}]}"#),
+        Some(SubscriptionFormat::SingBox)
+    );
+    assert_eq!(
+        detect_format(r#"{"version": 1, "servers": []}"#),
+        Some(SubscriptionFormat::Sip008)
+    );
+    assert_eq!(detect_format("<html></html>"), None);
+
+    let result = convert_uri_list(&encoded, crate::utils::tmpl::URI_LIST_TEMPLATE).unwrap();
+    assert_eq!(result.skipped.len(), 1);
+    assert_eq!(result.skipped[0].line, 6);
+    assert!(result.config.contains_key("proxy-groups"));
+
+    let proxies = result.config["proxies"].as_sequence().unwrap();
+    let names = proxies
+        .iter()
+        .map(|proxy| proxy["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["A node", "vm", "A node 2", "tr", "hy"]);
+
+    let expected = serde_yaml::from_str::<Vec<Mapping>>(
+        r#"
+        - name: A node
+          type: vless
+          server: a.example.com
+          port: 443
+          uuid: b831381d-6324-4d53-ad4f-8cda48b30811
+          udp: true
+          flow: xtls-rprx-vision
+          tls: true
+          servername: www.microsoft.com
+          client-fingerprint: chrome
+          reality-opts: { public-key: KEY, short-id: ab }
+        - name: vm
+          type: vmess
+          server: v.example.com
+          port: 443
+          uuid: b831381d-6324-4d53-ad4f-8cda48b30811
+          alterId: 0
+          cipher: auto
+          udp: true
+          tls: true
+          network: ws
+          ws-opts: { path: /ws, headers: { Host: cdn.example.com } }
+        - name: A node 2
+          type: ss
+          server: 1.2.3.4
+          port: 8388
+          cipher: aes-256-gcm
+          password: pass
+          udp: true
+        - name: tr
+          type: trojan
+          server: t.example.com
+          port: 443
+          password: secret
+          udp: true
+          sni: t.example.com
+          network: grpc
+          grpc-opts: { grpc-service-name: svc }
+        - name: hy
+          type: hysteria2
+          server: h.example.com
+          port: 8443
+          password: pass
+          sni: h.example.com
+          obfs: salamander
+          obfs-password: x
+          skip-cert-verify: true
+        "#,
+    )
+    .unwrap();
+    for (proxy, expected) in proxies.iter().zip(expected) {
+        assert_eq!(proxy.as_mapping().unwrap(), &expected);
+    }
+
+    // 导出后再导入得到相同的代理
+    let exported = export_uri_list(proxies);
+    assert!(exported
+        .links
+        .iter()
+        .all(|link| link.unsupported.is_empty()));
+    let template = "proxy-groups: []";
+    let imported = convert_uri_list(&exported.content, template).unwrap();
+    assert!(imported.skipped.is_empty());
+    assert_eq!(&imported.config["proxies"], &result.config["proxies"]);
+}
@@ -19,6 +19,7 @@ pub static PORTABLE_FLAG: OnceCell<bool> = OnceCell::new();
 pub static CLASH_CONFIG: &str = "config.yaml";
 pub static VERGE_CONFIG: &str = "verge.yaml";
 pub static PROFILE_YAML: &str = "profiles.yaml";
+pub static LATENCY_HISTORY: &str = "latency_history.yaml";
 
 /// init portable flag
 pub fn init_portable_flag() -> Result<()> {
@@ -185,6 +186,10 @@ pub fn profiles_path() -> Result<PathBuf> {
     Ok(app_home_dir()?.join(PROFILE_YAML))
 }
 
+pub fn latency_history_path() -> Result<PathBuf> {
+    Ok(app_home_dir()?.join(LATENCY_HISTORY))
+}
+
 #[cfg(target_os = "macos")]
 pub fn service_path() -> Result<PathBuf> {
     let res_dir = app_resources_dir()?;
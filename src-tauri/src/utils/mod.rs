@@ -1,4 +1,5 @@
 pub mod autostart;
+pub mod convert;
 pub mod dirs;
 pub mod help;
 pub mod i18n;
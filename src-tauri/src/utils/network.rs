@@ -409,7 +409,16 @@ impl NetworkManager {
             accept_invalid_certs,
             use_hwid,
         );
+        self.send_with_interrupt(url, request, timeout_secs).await
+    }
 
+    /// 发送已构建的请求，超时后中断
+    pub async fn send_with_interrupt(
+        &self,
+        url: &str,
+        request: RequestBuilder,
+        timeout_secs: Option<u64>,
+    ) -> Result<Response> {
         let timeout_duration = timeout_secs.unwrap_or(20);
 
         let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
@@ -795,7 +795,7 @@ pub async fn resolve_scheme(param: String) -> Result<()> {
                 log::info!(target:"app", "decoded subscription url: {url}");
 
                 // Deep link inside resolver is now executed via schedule_handle_deep_link
-                match PrfItem::from_url(url.as_ref(), name, None, None).await {
+                match PrfItem::from_url(url.as_ref(), name, None, None, None).await {
                     Ok(item) => {
                         let uid = item.uid.clone().unwrap();
                         let _ = wrap_err!(Config::profiles().data().append_item(item));
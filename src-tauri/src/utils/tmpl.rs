@@ -12,6 +12,25 @@ rules: []
 
 /// enhanced profile
 pub const ITEM_MERGE: &str = "# Profile Enhancement Merge Template for OutClash
+#
+# Plain keys are deep-merged into the profile: mappings are merged key by key,
+# any other value replaces the original one.
+#
+# Directives, usable at any level of nesting:
+#   prepend-<key>: [...]   insert items at the beginning of the list <key>
+#   append-<key>: [...]    add items to the end of the list <key>
+#   <key>!: ...            replace <key> as a whole instead of deep-merging
+#   delete-<key>: true     remove <key>
+#
+# Example:
+#   prepend-rules:
+#     - DOMAIN-SUFFIX,example.com,DIRECT
+#   dns:
+#     append-nameserver:
+#       - https://1.1.1.1/dns-query
+#   tun!:
+#     enable: false
+#   delete-hosts: true
 
 profile:
   store-selected: true
@@ -23,6 +42,12 @@ pub const ITEM_MERGE_EMPTY: &str = "# Profile Enhancement Merge Template for Out
 
 /// enhanced profile
 pub const ITEM_SCRIPT: &str = "// Define main function (script entry)
+//
+// The global `verge` helper library is available, e.g.
+//   verge.proxies.filter(config.proxies, /HK|Hong Kong/i)
+//   verge.proxies.renameInConfig(config, /^/, '[A] ')
+//   verge.profiles.proxies('another profile name or uid')
+//   verge.yaml / verge.base64 / verge.url.parse
 
 function main(config, profileName) {
   return config;
@@ -31,6 +56,18 @@ function main(config, profileName) {
 
 /// enhanced profile
 pub const ITEM_RULES: &str = "# Profile Enhancement Rules Template for OutClash
+#
+# Optional sections besides prepend / append / delete:
+#   delete-regex   remove entries whose name matches any regex
+#   delete-match   remove entries whose fields all match, e.g. { type: ss }
+#   insert         insert items before / after a named entry
+#   patch          edit fields of existing entries by name
+#
+# Example:
+#   delete-regex: ['^DOMAIN-SUFFIX,ads\\.']
+#   insert:
+#     - before: 'MATCH,PROXY'
+#       items: ['GEOIP,CN,DIRECT']
 
 prepend: []
 
@@ -41,6 +78,19 @@ delete: []
 
 /// enhanced profile
 pub const ITEM_PROXIES: &str = "# Profile Enhancement Proxies Template for OutClash
+#
+# Optional sections besides prepend / append / delete:
+#   delete-regex   remove entries whose name matches any regex
+#   delete-match   remove entries whose fields all match, e.g. { type: ss }
+#   insert         insert items before / after a named entry
+#   patch          edit fields of existing entries by name
+#
+# Example:
+#   delete-regex: ['(?i)expire|traffic']
+#   delete-match:
+#     - { type: ss }
+#   patch:
+#     'HK 01': { udp: true, dialer-proxy: relay }
 
 prepend: []
 
@@ -51,6 +101,19 @@ delete: []
 
 /// enhanced profile
 pub const ITEM_GROUPS: &str = "# Profile Enhancement Groups Template for OutClash
+#
+# Optional sections besides prepend / append / delete:
+#   delete-regex   remove entries whose name matches any regex
+#   delete-match   remove entries whose fields all match, e.g. { type: ss }
+#   insert         insert items before / after a named entry
+#   patch          edit fields of existing entries by name
+#
+# Example:
+#   insert:
+#     - after: PROXY
+#       items: [{ name: Auto, type: url-test, proxies: [DIRECT] }]
+#   patch:
+#     PROXY: { append-proxies: [Auto] }
 
 prepend: []
 
@@ -58,3 +121,28 @@ append: []
 
 delete: []
 ";
+
+/// template for uri list subscriptions
+pub const URI_LIST_TEMPLATE: &str = "# Subscription Template for URI Lists
+#
+# `proxies` is filled with the nodes converted from the share links,
+# groups can reference all of them with `include-all-proxies: true`.
+
+proxy-groups:
+  - name: PROXY
+    type: select
+    proxies: [Auto]
+    include-all-proxies: true
+  - name: Auto
+    type: url-test
+    url: https://cp.cloudflare.com/generate_204
+    interval: 300
+    include-all-proxies: true
+
+rules:
+  - IP-CIDR,127.0.0.0/8,DIRECT,no-resolve
+  - IP-CIDR,10.0.0.0/8,DIRECT,no-resolve
+  - IP-CIDR,172.16.0.0/12,DIRECT,no-resolve
+  - IP-CIDR,192.168.0.0/16,DIRECT,no-resolve
+  - MATCH,PROXY
+";
@@ -0,0 +1,89 @@
+use reqwest::StatusCode;
+use std::{fmt, time::Duration};
+
+/// 调用 mihomo 控制器时可能出现的错误
+#[derive(Debug)]
+pub enum MihomoError {
+    /// 连接失败、超时等传输层错误
+    Transport(reqwest::Error),
+    /// 控制器返回非 2xx 状态码，`message` 取自响应体中的 `message` 字段
+    Status { status: StatusCode, message: String },
+    /// 响应体无法解析为期望的结构
+    Decode(serde_json::Error),
+    /// secret 错误或缺失（401 / 403）
+    Unauthorized(String),
+    /// 内核近期持续无法连接，熔断期间直接失败，附带剩余冷却时间
+    CircuitOpen(Duration),
+    /// 控制器地址无法解析为 URL
+    InvalidUrl(String),
+}
+
+impl MihomoError {
+    /// 内核不可达（未启动或正在重启）
+    pub fn is_transport(&self) -> bool {
+        matches!(
+            self,
+            MihomoError::Transport(_) | MihomoError::CircuitOpen(_)
+        )
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            MihomoError::Status { status, .. } => Some(*status),
+            MihomoError::Unauthorized(_) => Some(StatusCode::UNAUTHORIZED),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MihomoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MihomoError::Transport(e) => write!(f, "failed to reach mihomo controller: {e}"),
+            MihomoError::Status { status, message } => {
+                write!(f, "mihomo controller returned {status}: {message}")
+            }
+            MihomoError::Decode(e) => write!(f, "failed to decode mihomo response: {e}"),
+            MihomoError::Unauthorized(message) => {
+                write!(f, "mihomo controller rejected the secret: {message}")
+            }
+            MihomoError::CircuitOpen(remaining) => write!(
+                f,
+                "mihomo controller is unreachable, retry in {}ms",
+                remaining.as_millis()
+            ),
+            MihomoError::InvalidUrl(server) => {
+                write!(f, "invalid mihomo controller address: {server}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MihomoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MihomoError::Transport(e) => Some(e),
+            MihomoError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for MihomoError {
+    fn from(e: reqwest::Error) -> Self {
+        MihomoError::Transport(e)
+    }
+}
+
+impl From<serde_json::Error> for MihomoError {
+    fn from(e: serde_json::Error) -> Self {
+        MihomoError::Decode(e)
+    }
+}
+
+/// 方便在返回 `Result<_, String>` 的 tauri 命令中直接使用 `?`
+impl From<MihomoError> for String {
+    fn from(e: MihomoError) -> Self {
+        e.to_string()
+    }
+}
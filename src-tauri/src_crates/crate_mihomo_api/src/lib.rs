@@ -1,15 +1,24 @@
-use reqwest::{Method, header::HeaderMap};
+use reqwest::{Method, StatusCode, Url, header::HeaderMap};
+use serde::de::DeserializeOwned;
 use serde_json::{Value, json};
 use std::time::Duration;
+pub mod error;
 pub mod model;
-pub use model::MihomoManager;
+pub mod policy;
+pub mod ws;
+pub use error::MihomoError;
+pub use model::*;
+pub use policy::{CircuitBreaker, RequestConfig};
+
+pub type MihomoResult<T> = Result<T, MihomoError>;
+
+const DEFAULT_TEST_URL: &str = "https://cp.cloudflare.com/generate_204";
 
 impl MihomoManager {
     pub fn new(mihomo_server: String, headers: HeaderMap) -> Self {
         let client = reqwest::ClientBuilder::new()
-            .default_headers(headers)
+            .default_headers(headers.clone())
             .no_proxy()
-            .timeout(Duration::from_secs(15))
             .pool_max_idle_per_host(5)
             .pool_idle_timeout(Duration::from_secs(15))
             .build()
@@ -18,97 +27,223 @@ impl MihomoManager {
         Self {
             mihomo_server,
             client,
+            headers,
+            unix_socket: None,
+            config: RequestConfig::default(),
+            breaker: CircuitBreaker::new(),
+        }
+    }
+
+    /// 通过 `external-controller-unix` 的 Unix domain socket 访问控制器
+    #[cfg(unix)]
+    pub fn new_unix(socket_path: std::path::PathBuf, headers: HeaderMap) -> Self {
+        let client = reqwest::ClientBuilder::new()
+            .default_headers(headers.clone())
+            .unix_socket(socket_path.clone())
+            .pool_max_idle_per_host(5)
+            .pool_idle_timeout(Duration::from_secs(15))
+            .build()
+            .expect("Failed to build reqwest client");
+
+        Self {
+            // 走 socket 时 host 不参与连接，仅用于拼接请求路径
+            mihomo_server: "http://localhost".into(),
+            client,
+            headers,
+            unix_socket: Some(socket_path),
+            config: RequestConfig::default(),
+            breaker: CircuitBreaker::new(),
         }
     }
 
+    pub fn with_request_config(mut self, config: RequestConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 使用外部共享的熔断器，使重新创建的实例继承内核的可用状态
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    pub fn circuit_breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    /// 由路径片段拼接请求地址，每个片段单独做百分号编码（`/` 也会被编码）
+    fn endpoint(&self, segments: &[&str]) -> MihomoResult<Url> {
+        let invalid = || MihomoError::InvalidUrl(self.mihomo_server.clone());
+        let mut url = Url::parse(&self.mihomo_server).map_err(|_| invalid())?;
+        url.path_segments_mut()
+            .map_err(|_| invalid())?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    /// 发送请求并检查状态码，非 2xx 响应会被转换为对应的 `MihomoError`
+    ///
+    /// 无法建立连接时按 `RequestConfig` 退避重试，连续失败会打开熔断
     async fn send_request(
         &self,
         method: Method,
-        url: String,
-        data: Option<serde_json::Value>,
-    ) -> Result<serde_json::Value, String> {
-        let client_response = self
-            .client
-            .request(method.clone(), &url)
-            .json(&data.unwrap_or(json!({})))
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
-
-        let response = match method {
-            Method::PATCH => {
-                let status = client_response.status();
-                if status.as_u16() == 204 {
-                    json!({"code": 204})
-                } else {
-                    client_response
-                        .json::<serde_json::Value>()
-                        .await
-                        .map_err(|e| e.to_string())?
+        url: Url,
+        data: Option<Value>,
+        timeout: Duration,
+    ) -> MihomoResult<reqwest::Response> {
+        if let Some(remaining) = self.breaker.remaining() {
+            return Err(MihomoError::CircuitOpen(remaining));
+        }
+
+        let data = data.unwrap_or(json!({}));
+        let mut attempt = 0;
+        let response = loop {
+            let result = self
+                .client
+                .request(method.clone(), url.clone())
+                .timeout(timeout)
+                .json(&data)
+                .send()
+                .await;
+
+            match result {
+                Ok(response) => {
+                    self.breaker.record_success();
+                    break response;
+                }
+                Err(e) if e.is_connect() && attempt < self.config.max_retries => {
+                    tokio::time::sleep(self.config.retry_base_delay * 2u32.pow(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    if e.is_connect() {
+                        self.breaker.record_failure(
+                            self.config.breaker_threshold,
+                            self.config.breaker_cooldown,
+                        );
+                    }
+                    return Err(e.into());
                 }
             }
-            Method::PUT => json!(client_response.text().await.map_err(|e| e.to_string())?),
-            _ => client_response
-                .json::<serde_json::Value>()
-                .await
-                .map_err(|e| e.to_string())?,
         };
-        Ok(response)
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|v| v["message"].as_str().map(str::to_string))
+            .unwrap_or(body);
+
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(MihomoError::Unauthorized(message))
+            }
+            _ => Err(MihomoError::Status { status, message }),
+        }
     }
 
-    pub async fn get_refresh_proxies(&self) -> Result<Value, String> {
-        let url = format!("{}/proxies", self.mihomo_server);
-        let proxies = self.send_request(Method::GET, url, None).await?;
-        Ok(proxies)
+    /// 请求并把响应体解析为 `T`
+    async fn request_json<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        url: Url,
+        data: Option<Value>,
+        timeout: Duration,
+    ) -> MihomoResult<T> {
+        let response = self.send_request(method, url, data, timeout).await?;
+        let bytes = response.bytes().await?;
+        Ok(serde_json::from_slice(&bytes)?)
     }
 
-    pub async fn get_providers_proxies(&self) -> Result<Value, String> {
-        let url = format!("{}/providers/proxies", self.mihomo_server);
-        let providers_proxies = self.send_request(Method::GET, url, None).await?;
-        Ok(providers_proxies)
+    /// 请求并忽略响应体（多为 204 No Content）
+    async fn request_empty(
+        &self,
+        method: Method,
+        url: Url,
+        data: Option<Value>,
+        timeout: Duration,
+    ) -> MihomoResult<()> {
+        self.send_request(method, url, data, timeout).await?;
+        Ok(())
     }
 
-    pub async fn close_all_connections(&self) -> Result<(), String> {
-        let url = format!("{}/connections", self.mihomo_server);
-        let response = self.send_request(Method::DELETE, url, None).await?;
-        if response["code"] == 204 {
-            Ok(())
-        } else {
-            Err(response["message"]
-                .as_str()
-                .unwrap_or("unknown error")
-                .to_string())
+    /// 转发任意控制器请求，`path` 为已编码的路径，可带查询参数
+    ///
+    /// 供前端在只开放 unix socket 时经由后端访问内核，响应体为空时返回 `null`，不是 JSON 时返回原文
+    pub async fn request_raw(
+        &self,
+        method: Method,
+        path: &str,
+        data: Option<Value>,
+        timeout: Duration,
+    ) -> MihomoResult<Value> {
+        let mut url = self.endpoint(&[])?;
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        url.set_path(path);
+        url.set_query(Some(query).filter(|query| !query.is_empty()));
+
+        let response = self.send_request(method, url, data, timeout).await?;
+        let text = response.text().await?;
+        if text.trim().is_empty() {
+            return Ok(Value::Null);
         }
+        Ok(serde_json::from_str(&text).unwrap_or(Value::String(text)))
     }
-}
 
-impl MihomoManager {
-    pub async fn is_mihomo_running(&self) -> Result<(), String> {
-        let url = format!("{}/version", self.mihomo_server);
-        let _response = self.send_request(Method::GET, url, None).await?;
-        Ok(())
+    /// 延迟测试的请求超时：内核侧测试超时加上余量
+    fn delay_timeout(&self, timeout: i32) -> Duration {
+        Duration::from_millis(timeout.max(0) as u64) + self.config.delay_timeout_margin
     }
 
-    pub async fn put_configs_force(&self, clash_config_path: &str) -> Result<(), String> {
-        let url = format!("{}/configs?force=true", self.mihomo_server);
+    pub async fn get_refresh_proxies(&self) -> MihomoResult<Proxies> {
+        let url = self.endpoint(&["proxies"])?;
+        let timeout = self.config.default_timeout;
+        self.request_json(Method::GET, url, None, timeout).await
+    }
+
+    pub async fn get_providers_proxies(&self) -> MihomoResult<ProxyProviders> {
+        let url = self.endpoint(&["providers", "proxies"])?;
+        let timeout = self.config.default_timeout;
+        self.request_json(Method::GET, url, None, timeout).await
+    }
+
+    pub async fn close_all_connections(&self) -> MihomoResult<()> {
+        let url = self.endpoint(&["connections"])?;
+        let timeout = self.config.default_timeout;
+        self.request_empty(Method::DELETE, url, None, timeout).await
+    }
+
+    pub async fn get_version(&self) -> MihomoResult<Version> {
+        let url = self.endpoint(&["version"])?;
+        let timeout = self.config.default_timeout;
+        self.request_json(Method::GET, url, None, timeout).await
+    }
+
+    pub async fn is_mihomo_running(&self) -> MihomoResult<()> {
+        self.get_version().await.map(|_| ())
+    }
+
+    pub async fn put_configs_force(&self, clash_config_path: &str) -> MihomoResult<()> {
+        let mut url = self.endpoint(&["configs"])?;
+        url.query_pairs_mut().append_pair("force", "true");
         let payload = serde_json::json!({
             "path": clash_config_path,
         });
-        let _response = self.send_request(Method::PUT, url, Some(payload)).await?;
-        Ok(())
+        let timeout = self.config.reload_timeout;
+        self.request_empty(Method::PUT, url, Some(payload), timeout)
+            .await
     }
 
-    pub async fn patch_configs(&self, config: serde_json::Value) -> Result<(), String> {
-        let url = format!("{}/configs", self.mihomo_server);
-        let response = self.send_request(Method::PATCH, url, Some(config)).await?;
-        if response["code"] == 204 {
-            Ok(())
-        } else {
-            Err(response["message"]
-                .as_str()
-                .unwrap_or("unknown error")
-                .to_string())
-        }
+    pub async fn patch_configs(&self, config: Value) -> MihomoResult<()> {
+        let url = self.endpoint(&["configs"])?;
+        let timeout = self.config.default_timeout;
+        self.request_empty(Method::PATCH, url, Some(config), timeout)
+            .await
     }
 
     pub async fn test_proxy_delay(
@@ -116,32 +251,117 @@ impl MihomoManager {
         name: &str,
         test_url: Option<String>,
         timeout: i32,
-    ) -> Result<serde_json::Value, String> {
-        let test_url = test_url.unwrap_or("https://cp.cloudflare.com/generate_204".to_string());
-        let url = format!(
-            "{}/proxies/{}/delay?url={}&timeout={}",
-            self.mihomo_server, name, test_url, timeout
-        );
-        let response = self.send_request(Method::GET, url, None).await?;
-        Ok(response)
-    }
-
-    pub async fn get_connections(&self) -> Result<serde_json::Value, String> {
-        let url = format!("{}/connections", self.mihomo_server);
-        let response = self.send_request(Method::GET, url, None).await?;
-        Ok(response)
-    }
-
-    pub async fn delete_connection(&self, id: &str) -> Result<(), String> {
-        let url = format!("{}/connections/{}", self.mihomo_server, id);
-        let response = self.send_request(Method::DELETE, url, None).await?;
-        if response["code"] == 204 {
-            Ok(())
-        } else {
-            Err(response["message"]
-                .as_str()
-                .unwrap_or("unknown error")
-                .to_string())
-        }
+    ) -> MihomoResult<ProxyDelay> {
+        let test_url = test_url.unwrap_or(DEFAULT_TEST_URL.to_string());
+        let mut url = self.endpoint(&["proxies", name, "delay"])?;
+        url.query_pairs_mut()
+            .append_pair("url", &test_url)
+            .append_pair("timeout", &timeout.to_string());
+        let timeout = self.delay_timeout(timeout);
+        self.request_json(Method::GET, url, None, timeout).await
+    }
+
+    pub async fn get_connections(&self) -> MihomoResult<Connections> {
+        let url = self.endpoint(&["connections"])?;
+        let timeout = self.config.default_timeout;
+        self.request_json(Method::GET, url, None, timeout).await
+    }
+
+    pub async fn delete_connection(&self, id: &str) -> MihomoResult<()> {
+        let url = self.endpoint(&["connections", id])?;
+        let timeout = self.config.default_timeout;
+        self.request_empty(Method::DELETE, url, None, timeout).await
+    }
+
+    pub async fn get_configs(&self) -> MihomoResult<Value> {
+        let url = self.endpoint(&["configs"])?;
+        let timeout = self.config.default_timeout;
+        self.request_json(Method::GET, url, None, timeout).await
+    }
+
+    pub async fn get_rules(&self) -> MihomoResult<Rules> {
+        let url = self.endpoint(&["rules"])?;
+        let timeout = self.config.default_timeout;
+        self.request_json(Method::GET, url, None, timeout).await
+    }
+
+    /// 切换 select 类型代理组的当前节点
+    pub async fn select_proxy(&self, group: &str, name: &str) -> MihomoResult<()> {
+        let url = self.endpoint(&["proxies", group])?;
+        let payload = serde_json::json!({
+            "name": name,
+        });
+        let timeout = self.config.default_timeout;
+        self.request_empty(Method::PUT, url, Some(payload), timeout)
+            .await
+    }
+
+    pub async fn test_group_delay(
+        &self,
+        group: &str,
+        test_url: Option<String>,
+        timeout: i32,
+    ) -> MihomoResult<GroupDelay> {
+        let test_url = test_url.unwrap_or(DEFAULT_TEST_URL.to_string());
+        let mut url = self.endpoint(&["group", group, "delay"])?;
+        url.query_pairs_mut()
+            .append_pair("url", &test_url)
+            .append_pair("timeout", &timeout.to_string());
+        let timeout = self.delay_timeout(timeout);
+        self.request_json(Method::GET, url, None, timeout).await
+    }
+
+    pub async fn update_proxy_provider(&self, name: &str) -> MihomoResult<()> {
+        let url = self.endpoint(&["providers", "proxies", name])?;
+        let timeout = self.config.provider_timeout;
+        self.request_empty(Method::PUT, url, None, timeout).await
+    }
+
+    pub async fn healthcheck_proxy_provider(&self, name: &str) -> MihomoResult<()> {
+        let url = self.endpoint(&["providers", "proxies", name, "healthcheck"])?;
+        let timeout = self.config.provider_timeout;
+        self.request_empty(Method::GET, url, None, timeout).await
+    }
+
+    pub async fn get_providers_rules(&self) -> MihomoResult<RuleProviders> {
+        let url = self.endpoint(&["providers", "rules"])?;
+        let timeout = self.config.default_timeout;
+        self.request_json(Method::GET, url, None, timeout).await
+    }
+
+    pub async fn update_rule_provider(&self, name: &str) -> MihomoResult<()> {
+        let url = self.endpoint(&["providers", "rules", name])?;
+        let timeout = self.config.provider_timeout;
+        self.request_empty(Method::PUT, url, None, timeout).await
+    }
+
+    /// 更新 GeoIP / GeoSite 等数据库
+    pub async fn upgrade_geo(&self) -> MihomoResult<()> {
+        let url = self.endpoint(&["configs", "geo"])?;
+        let timeout = self.config.reload_timeout;
+        self.request_empty(Method::POST, url, None, timeout).await
+    }
+
+    /// 让内核重新加载自身（由内核自行 exec）
+    pub async fn restart(&self) -> MihomoResult<()> {
+        let url = self.endpoint(&["restart"])?;
+        let timeout = self.config.reload_timeout;
+        self.request_empty(Method::POST, url, None, timeout).await
+    }
+
+    pub async fn flush_fakeip_cache(&self) -> MihomoResult<()> {
+        let url = self.endpoint(&["cache", "fakeip", "flush"])?;
+        let timeout = self.config.default_timeout;
+        self.request_empty(Method::POST, url, None, timeout).await
+    }
+
+    pub async fn dns_query(&self, name: &str, query_type: Option<String>) -> MihomoResult<Value> {
+        let query_type = query_type.unwrap_or("A".to_string());
+        let mut url = self.endpoint(&["dns", "query"])?;
+        url.query_pairs_mut()
+            .append_pair("name", name)
+            .append_pair("type", &query_type);
+        let timeout = self.config.default_timeout;
+        self.request_json(Method::GET, url, None, timeout).await
     }
 }
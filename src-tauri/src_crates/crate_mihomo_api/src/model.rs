@@ -1,5 +1,270 @@
+use crate::policy::{CircuitBreaker, RequestConfig};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, path::PathBuf};
+
 #[derive(Clone)]
 pub struct MihomoManager {
     pub(crate) mihomo_server: String,
     pub(crate) client: reqwest::Client,
+    pub(crate) headers: HeaderMap,
+    /// 通过 `external-controller-unix` 连接时的 socket 路径
+    pub(crate) unix_socket: Option<PathBuf>,
+    pub(crate) config: RequestConfig,
+    pub(crate) breaker: CircuitBreaker,
+}
+
+/// `GET /version`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Version {
+    pub version: String,
+    #[serde(default)]
+    pub meta: bool,
+}
+
+/// 单次延迟测试记录
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DelayHistory {
+    pub time: String,
+    pub delay: u32,
+}
+
+/// `GET /proxies/{name}/delay`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProxyDelay {
+    pub delay: u32,
+}
+
+/// `GET /group/{name}/delay`，节点名 -> 延迟
+pub type GroupDelay = HashMap<String, u32>;
+
+/// 代理节点，代理组也使用同一结构（带有 `all` / `now`）
+///
+/// 未声明的字段保存在 `extra` 中，序列化时原样输出
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Proxy {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub proxy_type: String,
+    #[serde(default)]
+    pub udp: bool,
+    #[serde(default)]
+    pub history: Vec<DelayHistory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub all: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub now: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Proxy {
+    pub fn is_group(&self) -> bool {
+        self.all.is_some()
+    }
+
+    /// 最近一次延迟测试结果
+    pub fn last_delay(&self) -> Option<u32> {
+        self.history.last().map(|h| h.delay)
+    }
+}
+
+/// `GET /proxies`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Proxies {
+    #[serde(default)]
+    pub proxies: HashMap<String, Proxy>,
+}
+
+impl Proxies {
+    /// 所有代理组（含 GLOBAL）
+    pub fn groups(&self) -> impl Iterator<Item = &Proxy> {
+        self.proxies.values().filter(|p| p.is_group())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Proxy> {
+        self.proxies.get(name)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscriptionInfo {
+    #[serde(rename = "Upload", default)]
+    pub upload: u64,
+    #[serde(rename = "Download", default)]
+    pub download: u64,
+    #[serde(rename = "Total", default)]
+    pub total: u64,
+    #[serde(rename = "Expire", default)]
+    pub expire: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyProvider {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    #[serde(default)]
+    pub vehicle_type: String,
+    #[serde(default)]
+    pub proxies: Vec<Proxy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription_info: Option<SubscriptionInfo>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `GET /providers/proxies`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProxyProviders {
+    #[serde(default)]
+    pub providers: HashMap<String, ProxyProvider>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleProvider {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    #[serde(default)]
+    pub vehicle_type: String,
+    #[serde(default)]
+    pub behavior: String,
+    #[serde(default)]
+    pub format: String,
+    #[serde(default)]
+    pub rule_count: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `GET /providers/rules`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuleProviders {
+    #[serde(default)]
+    pub providers: HashMap<String, RuleProvider>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rule {
+    #[serde(rename = "type")]
+    pub rule_type: String,
+    #[serde(default)]
+    pub payload: String,
+    #[serde(default)]
+    pub proxy: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `GET /rules`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rules {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionMetadata {
+    #[serde(default)]
+    pub network: String,
+    #[serde(rename = "type", default)]
+    pub conn_type: String,
+    #[serde(default)]
+    pub host: String,
+    #[serde(rename = "sourceIP", default)]
+    pub source_ip: String,
+    #[serde(default)]
+    pub source_port: String,
+    #[serde(rename = "destinationIP", default)]
+    pub destination_ip: String,
+    #[serde(default)]
+    pub destination_port: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process_path: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection {
+    pub id: String,
+    #[serde(default)]
+    pub metadata: ConnectionMetadata,
+    #[serde(default)]
+    pub upload: u64,
+    #[serde(default)]
+    pub download: u64,
+    #[serde(default)]
+    pub start: String,
+    #[serde(default)]
+    pub chains: Vec<String>,
+    #[serde(default)]
+    pub rule: String,
+    #[serde(default)]
+    pub rule_payload: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `GET /connections`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connections {
+    #[serde(default)]
+    pub download_total: u64,
+    #[serde(default)]
+    pub upload_total: u64,
+    /// 没有活动连接时内核返回 `null`
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub connections: Vec<Connection>,
+    #[serde(default)]
+    pub memory: u64,
+}
+
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// `/traffic` 推送，单位 B/s
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Traffic {
+    pub up: u64,
+    pub down: u64,
+}
+
+/// `/memory` 推送，单位 B
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Memory {
+    pub inuse: u64,
+    #[serde(default)]
+    pub oslimit: u64,
+}
+
+/// `/logs` 推送
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogEntry {
+    #[serde(rename = "type")]
+    pub level: String,
+    pub payload: String,
 }
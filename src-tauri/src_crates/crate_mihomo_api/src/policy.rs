@@ -0,0 +1,92 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// 请求超时与重试策略
+#[derive(Debug, Clone)]
+pub struct RequestConfig {
+    /// 普通查询类请求
+    pub default_timeout: Duration,
+    /// 重载配置、重启内核、更新 geo 数据库等耗时操作
+    pub reload_timeout: Duration,
+    /// 更新 provider、provider 健康检查
+    pub provider_timeout: Duration,
+    /// 延迟测试在测试超时之外额外等待的时间
+    pub delay_timeout_margin: Duration,
+    /// 连接被拒绝（内核正在重启）时的最大重试次数
+    pub max_retries: u32,
+    /// 首次重试前的等待时间，之后每次翻倍
+    pub retry_base_delay: Duration,
+    /// 连续多少次无法连接后打开熔断
+    pub breaker_threshold: u32,
+    /// 熔断打开后快速失败的时长
+    pub breaker_cooldown: Duration,
+}
+
+impl Default for RequestConfig {
+    fn default() -> Self {
+        Self {
+            default_timeout: Duration::from_secs(15),
+            reload_timeout: Duration::from_secs(60),
+            provider_timeout: Duration::from_secs(30),
+            delay_timeout_margin: Duration::from_secs(2),
+            max_retries: 3,
+            retry_base_delay: Duration::from_millis(200),
+            breaker_threshold: 3,
+            breaker_cooldown: Duration::from_secs(3),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// 简单的熔断器：连续无法连接内核时，在冷却期内直接返回错误，
+/// 冷却期结束后放行请求，成功一次即恢复
+///
+/// 克隆共享同一状态，可在多个 `MihomoManager` 之间复用
+#[derive(Debug, Clone, Default)]
+pub struct CircuitBreaker {
+    state: Arc<Mutex<BreakerState>>,
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 熔断打开时返回剩余的冷却时间
+    pub fn remaining(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .open_until
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.remaining().is_some()
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.failures = 0;
+        state.open_until = None;
+    }
+
+    pub fn record_failure(&self, threshold: u32, cooldown: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.failures = state.failures.saturating_add(1);
+        if state.failures >= threshold.max(1) {
+            state.open_until = Some(Instant::now() + cooldown);
+        }
+    }
+
+    /// 内核确认启动后调用，立即恢复请求
+    pub fn reset(&self) {
+        self.record_success();
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{Connections, LogEntry, Memory, MihomoManager, Traffic};
+use futures::{Stream, StreamExt, stream};
+use reqwest::header::HeaderMap;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{path::PathBuf, time::Duration};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream, connect_async,
+    tungstenite::{self, Message, client::IntoClientRequest},
+};
+
+// 断线重连的退避区间
+const RECONNECT_MIN_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(5);
+
+enum Socket {
+    Tcp(WebSocketStream<MaybeTlsStream<TcpStream>>),
+    #[cfg(unix)]
+    Unix(WebSocketStream<tokio::net::UnixStream>),
+}
+
+impl Socket {
+    async fn next(&mut self) -> Option<Result<Message, tungstenite::Error>> {
+        match self {
+            Socket::Tcp(socket) => socket.next().await,
+            #[cfg(unix)]
+            Socket::Unix(socket) => socket.next().await,
+        }
+    }
+}
+
+struct WsState {
+    url: String,
+    headers: HeaderMap,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    unix_socket: Option<PathBuf>,
+    socket: Option<Socket>,
+    delay: Duration,
+}
+
+impl WsState {
+    async fn connect(&mut self) -> bool {
+        let mut request = match self.url.as_str().into_client_request() {
+            Ok(request) => request,
+            Err(_) => return false,
+        };
+        for (key, value) in self.headers.iter() {
+            // Content-Type 等 HTTP 头对握手无意义，只需带上鉴权
+            if key == reqwest::header::AUTHORIZATION {
+                request.headers_mut().insert(key, value.clone());
+            }
+        }
+
+        #[cfg(unix)]
+        if let Some(path) = &self.unix_socket {
+            let Ok(stream) = tokio::net::UnixStream::connect(path).await else {
+                return false;
+            };
+            return match tokio_tungstenite::client_async(request, stream).await {
+                Ok((socket, _)) => {
+                    self.socket = Some(Socket::Unix(socket));
+                    true
+                }
+                Err(_) => false,
+            };
+        }
+
+        match connect_async(request).await {
+            Ok((socket, _)) => {
+                self.socket = Some(Socket::Tcp(socket));
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    async fn backoff(&mut self) {
+        tokio::time::sleep(self.delay).await;
+        self.delay = (self.delay * 2).min(RECONNECT_MAX_DELAY);
+    }
+}
+
+impl MihomoManager {
+    /// 把 `http(s)://` 控制器地址转换为对应的 `ws(s)://` 地址
+    fn ws_url(&self, path: &str) -> String {
+        let server = &self.mihomo_server;
+        let base = if let Some(rest) = server.strip_prefix("https://") {
+            format!("wss://{rest}")
+        } else if let Some(rest) = server.strip_prefix("http://") {
+            format!("ws://{rest}")
+        } else {
+            format!("ws://{server}")
+        };
+        format!("{base}{path}")
+    }
+
+    /// 订阅一个 WebSocket 端点，每条文本消息解析为 `T`
+    ///
+    /// 流不会主动结束：内核重启或连接断开时自动重连，丢弃流即可停止订阅
+    fn ws_stream<T>(&self, path: &str) -> impl Stream<Item = T> + Send + use<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let state = WsState {
+            url: self.ws_url(path),
+            headers: self.headers.clone(),
+            unix_socket: self.unix_socket.clone(),
+            socket: None,
+            delay: RECONNECT_MIN_DELAY,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            loop {
+                let Some(socket) = state.socket.as_mut() else {
+                    if !state.connect().await {
+                        state.backoff().await;
+                    }
+                    continue;
+                };
+
+                match socket.next().await {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Ok(item) = serde_json::from_str::<T>(&text) {
+                            state.delay = RECONNECT_MIN_DELAY;
+                            return Some((item, state));
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                        state.socket = None;
+                        state.backoff().await;
+                    }
+                    Some(Ok(_)) => {}
+                }
+            }
+        })
+    }
+
+    /// 原样转发 `path` 推送的 JSON 消息，供前端经由后端订阅
+    pub fn raw_stream(&self, path: &str) -> impl Stream<Item = Value> + Send + use<> {
+        self.ws_stream(path)
+    }
+
+    /// `/traffic`，每秒推送一次实时速率
+    pub fn traffic_stream(&self) -> impl Stream<Item = Traffic> + Send + use<> {
+        self.ws_stream("/traffic")
+    }
+
+    /// `/memory`，内核内存占用
+    pub fn memory_stream(&self) -> impl Stream<Item = Memory> + Send + use<> {
+        self.ws_stream("/memory")
+    }
+
+    /// `/logs`，`level` 为 `debug` / `info` / `warning` / `error` / `silent`
+    pub fn logs_stream(&self, level: Option<&str>) -> impl Stream<Item = LogEntry> + Send + use<> {
+        let level = level.unwrap_or("info");
+        self.ws_stream(&format!("/logs?level={level}"))
+    }
+
+    /// `/connections`，按 `interval`（毫秒）推送连接快照
+    pub fn connections_stream(
+        &self,
+        interval: Option<u64>,
+    ) -> impl Stream<Item = Connections> + Send + use<> {
+        let interval = interval.unwrap_or(1000);
+        self.ws_stream(&format!("/connections?interval={interval}"))
+    }
+}
@@ -5,3 +5,214 @@ fn test_mihomo_manager_init() {
     let _ = mihomo_api::MihomoManager::new("url".into(), HeaderMap::new());
     assert_eq!(true, true);
 }
+
+#[test]
+fn test_decode_proxies_keeps_unknown_fields() {
+    let body = serde_json::json!({
+        "proxies": {
+            "⚡️ Fastest": {
+                "name": "⚡️ Fastest",
+                "type": "URLTest",
+                "udp": true,
+                "history": [{"time": "2025-01-01T00:00:00Z", "delay": 120}],
+                "all": ["a", "b"],
+                "now": "a",
+                "fixed": ""
+            },
+            "a": {"name": "a", "type": "Vless", "udp": true, "history": [], "xudp": true}
+        }
+    });
+    let proxies: mihomo_api::Proxies = serde_json::from_value(body.clone()).unwrap();
+    let group = proxies.get("⚡️ Fastest").unwrap();
+    assert!(group.is_group());
+    assert_eq!(group.now.as_deref(), Some("a"));
+    assert_eq!(group.last_delay(), Some(120));
+    assert_eq!(proxies.groups().count(), 1);
+    assert_eq!(serde_json::to_value(&proxies).unwrap(), body);
+}
+
+#[test]
+fn test_decode_empty_connections() {
+    let body = serde_json::json!({
+        "downloadTotal": 10,
+        "uploadTotal": 20,
+        "connections": null,
+        "memory": 0
+    });
+    let connections: mihomo_api::Connections = serde_json::from_value(body).unwrap();
+    assert!(connections.connections.is_empty());
+    assert_eq!(connections.download_total, 10);
+}
+
+#[test]
+fn test_error_to_string() {
+    let err = mihomo_api::MihomoError::Status {
+        status: reqwest::StatusCode::NOT_FOUND,
+        message: "resource not found".into(),
+    };
+    assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND));
+    assert!(!err.is_transport());
+    let msg: String = err.into();
+    assert!(msg.contains("resource not found"));
+}
+
+#[tokio::test]
+async fn test_traffic_stream_reconnects() {
+    use futures::StreamExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // 每个连接只推送一条数据后断开，模拟内核重启
+    tokio::spawn(async move {
+        for up in [1u64, 2] {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let text = serde_json::json!({"up": up, "down": 0}).to_string();
+            futures::SinkExt::send(&mut ws, Message::text(text))
+                .await
+                .unwrap();
+            let _ = ws.close(None).await;
+        }
+    });
+
+    let manager = mihomo_api::MihomoManager::new(format!("http://{addr}"), HeaderMap::new());
+    let items: Vec<_> = tokio::time::timeout(
+        std::time::Duration::from_secs(10),
+        manager.traffic_stream().take(2).collect::<Vec<_>>(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(items.iter().map(|t| t.up).collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_unix_socket_transport() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let dir = std::env::temp_dir().join(format!("mihomo-api-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("controller.sock");
+    let _ = std::fs::remove_file(&path);
+    let listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 4096];
+        let n = stream.read(&mut buf).await.unwrap();
+        let request = String::from_utf8_lossy(&buf[..n]).to_string();
+        assert!(request.starts_with("GET /version"));
+        assert!(request.contains("authorization: Bearer secret"));
+        let body = r#"{"meta":true,"version":"v1.19.0"}"#;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+    });
+
+    let mut headers = HeaderMap::new();
+    headers.insert("Authorization", "Bearer secret".parse().unwrap());
+    let manager = mihomo_api::MihomoManager::new_unix(path.clone(), headers);
+    let version = manager.get_version().await.unwrap();
+    assert_eq!(version.version, "v1.19.0");
+    assert!(version.meta);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// 接受一个 HTTP 请求，返回其请求行，并以 `body` 作答
+async fn serve_once(listener: tokio::net::TcpListener, body: &'static str) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let mut buf = vec![0u8; 4096];
+    let n = stream.read(&mut buf).await.unwrap();
+    let request = String::from_utf8_lossy(&buf[..n]).to_string();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await.unwrap();
+    request.lines().next().unwrap_or_default().to_string()
+}
+
+#[tokio::test]
+async fn test_delay_url_is_percent_encoded() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(serve_once(listener, r#"{"delay":42}"#));
+
+    let manager = mihomo_api::MihomoManager::new(format!("http://{addr}"), HeaderMap::new());
+    let delay = manager
+        .test_proxy_delay("⚡️ Fastest/HK", Some("http://x.com/a?b=c&d".into()), 5000)
+        .await
+        .unwrap();
+    assert_eq!(delay.delay, 42);
+
+    let request_line = server.await.unwrap();
+    assert_eq!(
+        request_line,
+        "GET /proxies/%E2%9A%A1%EF%B8%8F%20Fastest%2FHK/delay\
+         ?url=http%3A%2F%2Fx.com%2Fa%3Fb%3Dc%26d&timeout=5000 HTTP/1.1"
+    );
+}
+
+#[tokio::test]
+async fn test_circuit_breaker_fails_fast() {
+    // 取一个空闲端口后立即释放，保证连接被拒绝
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let config = mihomo_api::RequestConfig {
+        max_retries: 1,
+        retry_base_delay: std::time::Duration::from_millis(10),
+        breaker_threshold: 2,
+        breaker_cooldown: std::time::Duration::from_secs(30),
+        ..Default::default()
+    };
+    let manager = mihomo_api::MihomoManager::new(format!("http://{addr}"), HeaderMap::new())
+        .with_request_config(config);
+
+    for _ in 0..2 {
+        let err = manager.get_version().await.unwrap_err();
+        assert!(matches!(err, mihomo_api::MihomoError::Transport(_)));
+    }
+    assert!(manager.circuit_breaker().is_open());
+    let err = manager.get_version().await.unwrap_err();
+    assert!(matches!(err, mihomo_api::MihomoError::CircuitOpen(_)));
+    assert!(err.is_transport());
+
+    // 共享的熔断器在内核恢复后可以手动复位
+    let shared = manager.circuit_breaker().clone();
+    shared.reset();
+    assert!(!manager.circuit_breaker().is_open());
+}
+
+#[tokio::test]
+async fn test_request_raw_keeps_path_and_query() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(serve_once(listener, r#"{"mode":"rule"}"#));
+
+    let manager = mihomo_api::MihomoManager::new(format!("http://{addr}"), HeaderMap::new());
+    let body = manager
+        .request_raw(
+            reqwest::Method::GET,
+            "/providers/proxies/a%20b?x=1",
+            None,
+            std::time::Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+    assert_eq!(body, serde_json::json!({"mode": "rule"}));
+
+    let request_line = server.await.unwrap();
+    assert_eq!(request_line, "GET /providers/proxies/a%20b?x=1 HTTP/1.1");
+}
@@ -24,7 +24,7 @@ import {
 import { useVisibility } from "@/hooks/use-visibility";
 import { useClashInfo } from "@/hooks/use-clash";
 import { useVerge } from "@/hooks/use-verge";
-import { createAuthSockette } from "@/utils/websocket";
+import { createAuthSockette, getStreamServer } from "@/utils/websocket";
 import parseTraffic from "@/utils/parse-traffic";
 import { isDebugEnabled, gc } from "@/services/api";
 import { ReactNode } from "react";
@@ -257,8 +257,9 @@ export const EnhancedTrafficStats = () => {
   useEffect(() => {
     if (!clashInfo || !pageVisible) return;
 
-    const { server, secret = "" } = clashInfo;
-    if (!server) return;
+    const { secret = "" } = clashInfo;
+    const server = getStreamServer(clashInfo);
+    if (server === null) return;
 
     // 清理现有连接的函数
     const cleanupSockets = () => {
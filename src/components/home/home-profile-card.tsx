@@ -57,7 +57,7 @@ interface ProfileExtra {
 
 export interface ProfileItem {
   uid: string;
-  type?: "local" | "remote" | "merge" | "script";
+  type?: "local" | "remote" | "aggregate" | "merge" | "script";
   name?: string;
   desc?: string;
   file?: string;
@@ -6,7 +6,7 @@ import { TrafficGraph, type TrafficRef } from "./traffic-graph";
 import { useVisibility } from "@/hooks/use-visibility";
 import parseTraffic from "@/utils/parse-traffic";
 import useSWRSubscription from "swr/subscription";
-import { createAuthSockette } from "@/utils/websocket";
+import { createAuthSockette, getStreamServer } from "@/utils/websocket";
 import { useTranslation } from "react-i18next";
 import { isDebugEnabled, gc } from "@/services/api";
 import { cn } from "@root/lib/utils";
@@ -41,9 +41,10 @@ export const LayoutTraffic = () => {
   >(
     clashInfo && pageVisible ? "getRealtimeTraffic" : null,
     (_key, { next }) => {
-      const { server = "", secret = "" } = clashInfo!;
+      const { secret = "" } = clashInfo!;
+      const server = getStreamServer(clashInfo!);
 
-      if (!server) {
+      if (server === null) {
         console.warn("[Traffic] 服务器地址为空，无法建立连接");
         next(null, { up: 0, down: 0 });
         return () => {};
@@ -97,9 +98,10 @@ export const LayoutTraffic = () => {
   >(
     clashInfo && pageVisible && displayMemory ? "getRealtimeMemory" : null,
     (_key, { next }) => {
-      const { server = "", secret = "" } = clashInfo!;
+      const { secret = "" } = clashInfo!;
+      const server = getStreamServer(clashInfo!);
 
-      if (!server) {
+      if (server === null) {
         console.warn("[Memory] 服务器地址为空，无法建立连接");
         next(null, { inuse: 0 });
         return () => {};
@@ -21,6 +21,7 @@ import { type JSONSchema7 } from "json-schema";
 import metaSchema from "meta-json-schema/schemas/meta-json-schema.json";
 import mergeSchema from "meta-json-schema/schemas/clash-verge-merge-json-schema.json";
 import pac from "types-pac/pac.d.ts?raw";
+import vergeLib from "@/services/verge-lib.d.ts?raw";
 import { Button } from "@/components/ui/button";
 import {
   Dialog,
@@ -120,6 +121,10 @@ const monacoInitialization = () => {
     ],
   });
   monaco.languages.typescript.javascriptDefaults.addExtraLib(pac, "pac.d.ts");
+  monaco.languages.typescript.javascriptDefaults.addExtraLib(
+    vergeLib,
+    "verge-lib.d.ts",
+  );
 
   initialized = true;
 };
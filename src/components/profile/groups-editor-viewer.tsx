@@ -330,6 +330,8 @@ export const GroupsEditorViewer = (props: Props) => {
   const [prependSeq, setPrependSeq] = useState<IProxyGroupConfig[]>([]);
   const [appendSeq, setAppendSeq] = useState<IProxyGroupConfig[]>([]);
   const [deleteSeq, setDeleteSeq] = useState<string[]>([]);
+  // 可视化编辑器不支持的字段（insert、patch 等），保存时原样写回
+  const [restSeq, setRestSeq] = useState<Record<string, any>>({});
 
   const filteredPrependSeq = useMemo(
     () => prependSeq.filter((group) => match(group.name)),
@@ -395,6 +397,7 @@ export const GroupsEditorViewer = (props: Props) => {
       setPrependSeq(obj?.prepend || []);
       setAppendSeq(obj?.append || []);
       setDeleteSeq(obj?.delete || []);
+      setRestSeq(obj || {});
       setPrevData(data);
       setCurrData(data);
     } catch (error) {
@@ -408,7 +411,12 @@ export const GroupsEditorViewer = (props: Props) => {
         try {
           setCurrData(
             yaml.dump(
-              { prepend: prependSeq, append: appendSeq, delete: deleteSeq },
+              {
+                ...restSeq,
+                prepend: prependSeq,
+                append: appendSeq,
+                delete: deleteSeq,
+              },
               { forceQuotes: true },
             ),
           );
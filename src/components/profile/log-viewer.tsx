@@ -27,7 +27,7 @@ export const LogViewer = (props: Props) => {
 
   // Вспомогательная функция для определения варианта Badge
   const getLogLevelVariant = (level: string): "destructive" | "secondary" => {
-    return level === "error" || level === "exception"
+    return level === "error" || level === "exception" || level === "limit"
       ? "destructive"
       : "secondary";
   };
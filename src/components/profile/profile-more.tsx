@@ -50,7 +50,9 @@ export const ProfileMore = (props: Props) => {
     }
   });
 
-  const hasError = !!logInfo.find((e) => e[0] === "exception");
+  const hasError = !!logInfo.find(
+    (e) => e[0] === "exception" || e[0] === "limit",
+  );
 
   const menuItems = [
     { label: "Edit File", handler: onEditFile, icon: FileText },
@@ -221,6 +221,8 @@ export const ProfileViewer = forwardRef<ProfileViewerRef, Props>(
           if (!form.type) throw new Error("`Type` should not be null");
           if (form.type === "remote" && !form.url)
             throw new Error("The URL should not be null");
+          if (form.type === "aggregate" && !form.option?.sources?.length)
+            throw new Error("Select at least one profile to aggregate");
 
           // Clean option fields: only send what user actually set
           let option = form.option ? { ...form.option } : undefined;
@@ -272,7 +274,9 @@ export const ProfileViewer = forwardRef<ProfileViewerRef, Props>(
             const newRemoteLocal = (after?.items || []).find(
               (i: any) =>
                 i &&
-                (i.type === "remote" || i.type === "local") &&
+                (i.type === "remote" ||
+                  i.type === "local" ||
+                  i.type === "aggregate") &&
                 i.uid &&
                 !beforeUids.has(i.uid),
             );
@@ -309,6 +313,33 @@ export const ProfileViewer = forwardRef<ProfileViewerRef, Props>(
     const formType = watch("type");
     const isRemote = formType === "remote";
     const isLocal = formType === "local";
+    const isAggregate = formType === "aggregate";
+
+    // 聚合订阅只能引用远程和本地订阅
+    const sources = watch("option.sources") ?? [];
+    const sourceCandidates = (profiles?.items ?? []).filter(
+      (i) =>
+        (i.type === "remote" || i.type === "local") &&
+        i.uid !== form.getValues("uid"),
+    );
+    const toggleSource = (uid: string, checked: boolean) => {
+      setValue(
+        "option.sources",
+        checked
+          ? [...sources, { uid }]
+          : sources.filter((source) => source.uid !== uid),
+      );
+    };
+    const setSourcePrefix = (uid: string, prefix: string) => {
+      setValue(
+        "option.sources",
+        sources.map((source) =>
+          source.uid === uid
+            ? { ...source, prefix: prefix || undefined }
+            : source,
+        ),
+      );
+    };
 
     return (
       <Dialog open={open} onOpenChange={setOpen}>
@@ -438,6 +469,7 @@ export const ProfileViewer = forwardRef<ProfileViewerRef, Props>(
                         <SelectContent>
                           <SelectItem value="remote">Remote</SelectItem>
                           <SelectItem value="local">Local</SelectItem>
+                          <SelectItem value="aggregate">Aggregate</SelectItem>
                         </SelectContent>
                       </Select>
                     </FormItem>
@@ -497,7 +529,7 @@ export const ProfileViewer = forwardRef<ProfileViewerRef, Props>(
                     <FormControl>
                       <Input
                         type="file"
-                        accept=".yml,.yaml"
+                        accept=".yml,.yaml,.json,.txt"
                         onChange={(e) => {
                           const file = e.target.files?.[0];
                           if (file) {
@@ -518,6 +550,47 @@ export const ProfileViewer = forwardRef<ProfileViewerRef, Props>(
                   </FormItem>
                 )}
 
+                {isAggregate && (
+                  <FormItem>
+                    <FormLabel>{t("Aggregated Profiles")}</FormLabel>
+                    <div className="space-y-2 rounded-md border p-4">
+                      {sourceCandidates.length === 0 && (
+                        <p className="text-sm text-muted-foreground">
+                          {t("No profiles to aggregate")}
+                        </p>
+                      )}
+                      {sourceCandidates.map((item) => {
+                        const source = sources.find((s) => s.uid === item.uid);
+                        return (
+                          <div
+                            key={item.uid}
+                            className="flex items-center gap-2"
+                          >
+                            <Switch
+                              checked={!!source}
+                              onCheckedChange={(checked) =>
+                                toggleSource(item.uid, checked)
+                              }
+                            />
+                            <span className="flex-1 truncate text-sm">
+                              {item.name || item.uid}
+                            </span>
+                            <Input
+                              className="h-8 w-28"
+                              placeholder={t("Name Prefix")}
+                              value={source?.prefix ?? ""}
+                              disabled={!source}
+                              onChange={(e) =>
+                                setSourcePrefix(item.uid, e.target.value)
+                              }
+                            />
+                          </div>
+                        );
+                      })}
+                    </div>
+                  </FormItem>
+                )}
+
                 {isRemote && (
                   <div className="space-y-4 rounded-md border p-4">
                     <FormField
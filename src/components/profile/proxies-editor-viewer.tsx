@@ -134,6 +134,8 @@ export const ProxiesEditorViewer = (props: Props) => {
   const [prependSeq, setPrependSeq] = useState<IProxyConfig[]>([]);
   const [appendSeq, setAppendSeq] = useState<IProxyConfig[]>([]);
   const [deleteSeq, setDeleteSeq] = useState<string[]>([]);
+  // 可视化编辑器不支持的字段（insert、patch 等），保存时原样写回
+  const [restSeq, setRestSeq] = useState<Record<string, any>>({});
 
   const filteredPrependSeq = useMemo(
     () => prependSeq.filter((proxy) => match(proxy.name)),
@@ -241,6 +243,7 @@ export const ProxiesEditorViewer = (props: Props) => {
     setPrependSeq(obj?.prepend || []);
     setAppendSeq(obj?.append || []);
     setDeleteSeq(obj?.delete || []);
+    setRestSeq(obj || {});
     setPrevData(data);
     setCurrData(data);
   };
@@ -256,6 +259,7 @@ export const ProxiesEditorViewer = (props: Props) => {
       setPrependSeq(obj?.prepend || []);
       setAppendSeq(obj?.append || []);
       setDeleteSeq(obj?.delete || []);
+      setRestSeq(obj || {});
     } catch (e) {
       console.error("Error parsing YAML in visualization mode:", e);
     }
@@ -267,7 +271,12 @@ export const ProxiesEditorViewer = (props: Props) => {
         try {
           setCurrData(
             yaml.dump(
-              { prepend: prependSeq, append: appendSeq, delete: deleteSeq },
+              {
+                ...restSeq,
+                prepend: prependSeq,
+                append: appendSeq,
+                delete: deleteSeq,
+              },
               { forceQuotes: true },
             ),
           );
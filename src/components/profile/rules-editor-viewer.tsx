@@ -334,6 +334,8 @@ export const RulesEditorViewer = (props: Props) => {
   const [prependSeq, setPrependSeq] = useState<string[]>([]);
   const [appendSeq, setAppendSeq] = useState<string[]>([]);
   const [deleteSeq, setDeleteSeq] = useState<string[]>([]);
+  // 可视化编辑器不支持的字段（insert、patch 等），保存时原样写回
+  const [restSeq, setRestSeq] = useState<Record<string, any>>({});
 
   const filteredPrependSeq = useMemo(
     () => prependSeq.filter((rule) => match(rule)),
@@ -383,6 +385,7 @@ export const RulesEditorViewer = (props: Props) => {
       setPrependSeq(obj?.prepend || []);
       setAppendSeq(obj?.append || []);
       setDeleteSeq(obj?.delete || []);
+      setRestSeq(obj || {});
       setPrevData(data);
       setCurrData(data);
     } catch (error) {
@@ -397,6 +400,7 @@ export const RulesEditorViewer = (props: Props) => {
       setPrependSeq(obj?.prepend || []);
       setAppendSeq(obj?.append || []);
       setDeleteSeq(obj?.delete || []);
+      setRestSeq(obj || {});
     } catch (e) {
       // Ignore parsing errors while typing
     }
@@ -408,7 +412,12 @@ export const RulesEditorViewer = (props: Props) => {
         try {
           setCurrData(
             yaml.dump(
-              { prepend: prependSeq, append: appendSeq, delete: deleteSeq },
+              {
+                ...restSeq,
+                prepend: prependSeq,
+                append: appendSeq,
+                delete: deleteSeq,
+              },
               { forceQuotes: true },
             ),
           );
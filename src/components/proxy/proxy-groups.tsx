@@ -15,7 +15,6 @@ import {
   deleteConnection,
   getGroupProxyDelays,
 } from "@/services/api";
-import { useProfiles } from "@/hooks/use-profiles";
 import { useVerge } from "@/hooks/use-verge";
 import { BaseEmpty } from "../base";
 import { useRenderList } from "./use-render-list";
@@ -63,7 +62,6 @@ export const ProxyGroups = memo((props: Props) => {
 
   const { renderList, onProxies, onHeadState } = useRenderList(mode);
   const { verge } = useVerge();
-  const { current, patchCurrent } = useProfiles();
   const timeout = verge?.default_latency_timeout || 10000;
 
   const virtuosoRef = useRef<VirtuosoHandle>(null);
@@ -113,18 +111,6 @@ export const ProxyGroups = memo((props: Props) => {
           });
         });
       }
-
-      if (!current || !current.selected) return;
-      const index = current.selected.findIndex(
-        (item) => item.name === group.name,
-      );
-
-      if (index < 0) {
-        current.selected.push({ name, now: proxy.name });
-      } else {
-        current.selected[index] = { name, now: proxy.name };
-      }
-      await patchCurrent({ selected: current.selected });
     },
   );
 
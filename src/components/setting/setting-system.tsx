@@ -110,7 +110,10 @@ const SettingSystem = ({ onError }: Props) => {
   const { profiles } = useProfiles();
   const hasProfiles = useMemo(() => {
     const items = profiles?.items ?? [];
-    return items.some((p) => p.type === "local" || p.type === "remote");
+    return items.some(
+      (p) =>
+        p.type === "local" || p.type === "remote" || p.type === "aggregate",
+    );
   }, [profiles]);
 
   const {
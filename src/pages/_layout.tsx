@@ -19,6 +19,7 @@ import { useListen } from "@/hooks/use-listen";
 import { listen } from "@tauri-apps/api/event";
 import { useClashInfo } from "@/hooks/use-clash";
 import { initGlobalLogService } from "@/services/global-log-service";
+import { getStreamServer } from "@/utils/websocket";
 import { invoke } from "@tauri-apps/api/core";
 import { showNotice } from "@/services/noticeService";
 import { Toaster } from "@/components/ui/sonner";
@@ -200,7 +201,8 @@ const Layout = () => {
   // Initialize the global logging service
   useEffect(() => {
     if (clashInfo) {
-      const { server = "", secret = "" } = clashInfo;
+      const { secret = "" } = clashInfo;
+      const server = getStreamServer(clashInfo);
       initGlobalLogService(server, secret, enableLog, "info");
     }
   }, [clashInfo, enableLog]);
@@ -101,7 +101,7 @@ const MinimalHomePage: React.FC = () => {
   const profileItems = useMemo(() => {
     const items =
       profiles && Array.isArray(profiles.items) ? profiles.items : [];
-    const allowedTypes = ["local", "remote"];
+    const allowedTypes = ["local", "remote", "aggregate"];
     return items.filter((i: any) => i && allowedTypes.includes(i.type!));
   }, [profiles]);
 
@@ -11,6 +11,7 @@ import { useLocalStorage } from "foxact/use-local-storage";
 import { Play, Pause, Trash2 } from "lucide-react";
 import { LogLevel } from "@/hooks/use-log-data";
 import { useClashInfo } from "@/hooks/use-clash";
+import { getStreamServer } from "@/utils/websocket";
 import { useEnableLog } from "@/services/states";
 import { BaseEmpty } from "@/components/base/base-empty";
 import LogItem from "@/components/log/log-item";
@@ -72,15 +73,15 @@ const LogPage = () => {
   const handleLogLevelChange = (newLevel: LogLevel) => {
     setLogLevel(newLevel);
     if (clashInfo) {
-      const { server = "", secret = "" } = clashInfo;
-      changeLogLevel(newLevel, server, secret);
+      const { secret = "" } = clashInfo;
+      changeLogLevel(newLevel, getStreamServer(clashInfo), secret);
     }
   };
 
   const handleToggleLog = () => {
     if (clashInfo) {
-      const { server = "", secret = "" } = clashInfo;
-      toggleLogEnabled(server, secret);
+      const { secret = "" } = clashInfo;
+      toggleLogEnabled(getStreamServer(clashInfo), secret);
       setEnableLog(!enableLog);
     }
   };
@@ -111,7 +111,7 @@ const ProfilePage = () => {
   const profileItems = useMemo(() => {
     const items =
       profiles && Array.isArray(profiles.items) ? profiles.items : [];
-    const type1 = ["local", "remote"];
+    const type1 = ["local", "remote", "aggregate"];
     return items.filter((i) => i && type1.includes(i.type!));
   }, [profiles]);
 
@@ -16,7 +16,7 @@ import {
   forceRefreshProxies,
 } from "@/services/cmds";
 import { useClashInfo } from "@/hooks/use-clash";
-import { createAuthSockette } from "@/utils/websocket";
+import { createAuthSockette, getStreamServer } from "@/utils/websocket";
 import { useVisibility } from "@/hooks/use-visibility";
 import { listen } from "@tauri-apps/api/event";
 
@@ -297,8 +297,9 @@ export const AppDataProvider = ({
     (_key, { next }) => {
       if (!clashInfo || !pageVisible) return () => {};
 
-      const { server = "", secret = "" } = clashInfo;
-      if (!server) return () => {};
+      const { secret = "" } = clashInfo;
+      const server = getStreamServer(clashInfo);
+      if (server === null) return () => {};
 
       console.log(
         `[Connections][${AppDataProvider.name}] Connecting: ${server}/connections`,
@@ -393,8 +394,9 @@ export const AppDataProvider = ({
     (_key, { next }) => {
       if (!clashInfo || !pageVisible) return () => {};
 
-      const { server = "", secret = "" } = clashInfo;
-      if (!server) return () => {};
+      const { secret = "" } = clashInfo;
+      const server = getStreamServer(clashInfo);
+      if (server === null) return () => {};
 
       console.log(
         `[Traffic][${AppDataProvider.name}] Connecting: ${server}/traffic`,
@@ -465,8 +467,9 @@ export const AppDataProvider = ({
     (_key, { next }) => {
       if (!clashInfo || !pageVisible) return () => {};
 
-      const { server = "", secret = "" } = clashInfo;
-      if (!server) return () => {};
+      const { secret = "" } = clashInfo;
+      const server = getStreamServer(clashInfo);
+      if (server === null) return () => {};
 
       console.log(
         `[Memory][${AppDataProvider.name}] Connecting: ${server}/memory`,
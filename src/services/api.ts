@@ -1,15 +1,31 @@
-import axios, { AxiosInstance } from "axios";
+import axios, { AxiosAdapter, AxiosInstance } from "axios";
 import { getClashInfo } from "./cmds";
 import { invoke } from "@tauri-apps/api/core";
 
 let instancePromise: Promise<AxiosInstance> = null!;
 
+// TCP 控制器关闭、只开放 unix socket 时，请求经由后端转发
+const backendAdapter: AxiosAdapter = async (config) => {
+  const path = axios.getUri({ url: config.url, params: config.params });
+  const data =
+    typeof config.data === "string" ? JSON.parse(config.data) : config.data;
+  const body = await invoke<any>("clash_api_request", {
+    method: config.method ?? "get",
+    path: path.startsWith("/") ? path : `/${path}`,
+    data: data ?? null,
+    timeout: config.timeout || null,
+  });
+  return { data: body, status: 200, statusText: "OK", headers: {}, config };
+};
+
 async function getInstancePromise() {
   let server = "";
   let secret = "";
+  let useBackend = false;
 
   try {
     const info = await getClashInfo();
+    useBackend = !info?.server && !!info?.unix_socket;
 
     if (info?.server) {
       server = info.server;
@@ -25,6 +41,7 @@ async function getInstancePromise() {
     baseURL: `http://${server}`,
     headers: secret ? { Authorization: `Bearer ${secret}` } : {},
     timeout: 15000,
+    ...(useBackend ? { adapter: backendAdapter } : {}),
   });
   axiosIns.interceptors.response.use((r) => r.data);
   return axiosIns;
@@ -93,9 +110,9 @@ export const getProxyDelay = async (
 };
 
 /// Update the Proxy Choose
+// 由后端切换并记录到当前订阅的 selected
 export const updateProxy = async (group: string, proxy: string) => {
-  const instance = await getAxios();
-  return instance.put(`/proxies/${encodeURIComponent(group)}`, { name: proxy });
+  return invoke<void>("clash_api_select_proxy", { group, name: proxy });
 };
 
 // get proxy
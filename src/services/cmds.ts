@@ -69,6 +69,36 @@ export async function reorderProfile(activeId: string, overId: string) {
   });
 }
 
+export async function getGlobalChain() {
+  return invoke<IGlobalChainItem[]>("get_global_chain");
+}
+
+export async function patchGlobalChain(chain: IGlobalChainItem[]) {
+  return invoke<void>("patch_global_chain", { chain });
+}
+
+export async function patchProfileChain(index: string, chain: string[]) {
+  return invoke<void>("patch_profile_chain", { index, chain });
+}
+
+export async function importRuleList(
+  index: string,
+  source: { text?: string; path?: string },
+  policy: string | null,
+  append: boolean,
+) {
+  return invoke<IRuleListImport>("import_rule_list", {
+    index,
+    ...source,
+    policy,
+    append,
+  });
+}
+
+export async function getChainUsers(index: string) {
+  return invoke<string[]>("get_chain_users", { index });
+}
+
 export async function updateProfile(index: string, option?: IProfileOption) {
   return invoke<void>("update_profile", { index, option });
 }
@@ -110,6 +140,25 @@ export async function getRuntimeLogs() {
   return invoke<Record<string, [string, string][]>>("get_runtime_logs");
 }
 
+export async function getRuntimeTrace() {
+  if (!isTauriEnv) return [] as IEnhanceStep[];
+  return invoke<IEnhanceStep[]>("get_runtime_trace");
+}
+
+export async function getRuntimeLint() {
+  if (!isTauriEnv) return [] as IConfigLint[];
+  return invoke<IConfigLint[]>("get_runtime_lint");
+}
+
+export async function matchRuntimeRule(query: IRouteQuery) {
+  return invoke<IRouteResult>("match_runtime_rule", { query });
+}
+
+export async function getEnhancePipeline() {
+  if (!isTauriEnv) return [] as IEnhancePass[];
+  return invoke<IEnhancePass[]>("get_enhance_pipeline");
+}
+
 export async function patchClashConfig(payload: Partial<IConfigData>) {
   if (!isTauriEnv) return;
   return invoke<void>("patch_clash_config", { payload });
@@ -251,7 +300,7 @@ export async function cmdGetProxyDelay(
   );
 
   try {
-    name = encodeURIComponent(name);
+    // 名称由后端负责编码
     const result = await invoke<{ delay: number }>(
       "clash_api_get_proxy_delay",
       {
@@ -292,6 +341,24 @@ export async function cmdTestDelay(url: string) {
   return invoke<number>("test_delay", { url });
 }
 
+/// 批量测速，进度通过 `verge://delay-test-progress` 事件推送
+export async function cmdBatchTestDelay(options: {
+  group?: string;
+  concurrency?: number;
+  timeout?: number;
+  url?: string;
+}) {
+  return invoke<IDelayTestResult[]>("clash_api_batch_test_delay", options);
+}
+
+export async function getLatencyStats(sortBy?: "median" | "jitter" | "loss") {
+  return invoke<ILatencyStats[]>("get_latency_stats", { sortBy });
+}
+
+export async function clearLatencyHistory() {
+  return invoke<void>("clear_latency_history");
+}
+
 export async function invoke_uwp_tool() {
   return invoke<void>("invoke_uwp_tool").catch((err) =>
     showNotice("error", err?.message || err.toString(), 1500),
@@ -474,3 +541,14 @@ export async function createProfileFromShareLink(
 ) {
   return invoke<void>("create_profile_from_share_link", { link, templateName });
 }
+
+export async function exportShareLinks(index?: string, names?: string[]) {
+  return invoke<IShareLink[]>("export_share_links", { index, names });
+}
+
+export async function exportProfileSubscription(index: string, path?: string) {
+  return invoke<IUriListExport>("export_profile_subscription", {
+    index,
+    path,
+  });
+}
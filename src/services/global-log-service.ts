@@ -62,7 +62,7 @@ const buildWSUrl = (server: string, logLevel: LogLevel) => {
 let globalLogSocket: any = null;
 
 export const initGlobalLogService = (
-  server: string,
+  server: string | null,
   secret: string,
   enabled: boolean = false,
   logLevel: LogLevel = "info",
@@ -73,7 +73,7 @@ export const initGlobalLogService = (
   setEnabled(enabled);
 
   // 如果不启用或没有服务器信息，则不初始化
-  if (!enabled || !server) {
+  if (!enabled || server === null) {
     closeGlobalLogConnection();
     return;
   }
@@ -85,7 +85,7 @@ export const initGlobalLogService = (
   const wsUrl = buildWSUrl(server, logLevel);
   console.log(`[GlobalLog] Connecting to log service: ${wsUrl}`);
 
-  if (!server) {
+  if (server === null) {
     console.warn(
       "[GlobalLog] Server URL is empty, cannot establish connection",
     );
@@ -143,25 +143,28 @@ export const closeGlobalLogConnection = () => {
 // 切换日志级别
 export const changeLogLevel = (
   level: LogLevel,
-  server: string,
+  server: string | null,
   secret: string,
 ) => {
   const { enabled } = useGlobalLogStore.getState();
   useGlobalLogStore.setState({ currentLevel: level });
 
-  if (enabled && server) {
+  if (enabled && server !== null) {
     initGlobalLogService(server, secret, enabled, level);
   }
 };
 
 // 切换启用状态
-export const toggleLogEnabled = (server: string, secret: string) => {
+export const toggleLogEnabled = (
+  server: string | null,
+  secret: string,
+) => {
   const { enabled, currentLevel } = useGlobalLogStore.getState();
   const newEnabled = !enabled;
 
   useGlobalLogStore.setState({ enabled: newEnabled });
 
-  if (newEnabled && server) {
+  if (newEnabled && server !== null) {
     initGlobalLogService(server, secret, newEnabled, currentLevel);
   } else {
     closeGlobalLogConnection();
@@ -169,6 +169,96 @@ interface IConnections {
  * Some interface for command
  */
 
+interface IListChange {
+  path: string;
+  index: number;
+  value: string;
+}
+
+interface IEnhancePass {
+  name: string;
+  description: string;
+  inputs: string[];
+  enabled: boolean;
+}
+
+interface IEnhanceStep {
+  step: string;
+  uid?: string;
+  diff: {
+    added: string[];
+    removed: string[];
+    changed: string[];
+    inserted: IListChange[];
+    deleted: IListChange[];
+  };
+}
+
+interface IConfigLint {
+  level: "error" | "warning";
+  kind: string;
+  path: string;
+  message: string;
+  step?: string;
+  uid?: string;
+}
+
+interface IRuleListImport {
+  rules: string[];
+  skipped: { line: number; text: string; reason: string }[];
+}
+
+interface IShareLink {
+  name: string;
+  link?: string;
+  unsupported: string[];
+  error?: string;
+}
+
+interface IUriListExport {
+  content: string;
+  links: IShareLink[];
+}
+
+interface IRouteQuery {
+  domain?: string;
+  ip?: string;
+  port?: number;
+  network?: "tcp" | "udp";
+  process?: string;
+}
+
+interface IRouteResult {
+  path?: string;
+  rule?: string;
+  target: string;
+  undecidable: { path: string; rule: string; reason: string }[];
+}
+
+interface ILatencyStats {
+  name: string;
+  last?: number;
+  median?: number;
+  jitter?: number;
+  loss: number;
+  samples: number;
+}
+
+interface IDelayTestResult {
+  name: string;
+  delay?: number;
+  error?: string;
+  stats: ILatencyStats;
+}
+
+interface IDelayTestProgress {
+  done: number;
+  total: number;
+  name: string;
+  delay?: number;
+  error?: string;
+}
+
 interface IClashInfo {
   // status: string;
   mixed_port?: number; // clash mixed port
@@ -177,13 +267,14 @@ interface IClashInfo {
   tproxy_port?: number; // clash tproxy port
   port?: number; // clash http port
   server?: string; // external-controller
+  unix_socket?: string; // external-controller-unix
   secret?: string;
 }
 
 interface IProfileItem {
   currentProfile: any;
   uid: string;
-  type?: "local" | "remote" | "merge" | "script";
+  type?: "local" | "remote" | "aggregate" | "merge" | "script";
   name?: string;
   desc?: string;
   file?: string;
@@ -200,6 +291,7 @@ interface IProfileItem {
     expire: number;
   };
   option?: IProfileOption;
+  chain?: string[];
   home?: string;
   support_url?: string;
   announce?: string;
@@ -212,6 +304,7 @@ interface IProfileOption {
   self_proxy?: boolean;
   update_interval?: number;
   update_always?: boolean;
+  uri_template?: string;
   timeout_seconds?: number;
   danger_accept_invalid_certs?: boolean;
   merge?: string;
@@ -219,12 +312,24 @@ interface IProfileOption {
   rules?: string;
   proxies?: string;
   groups?: string;
+  sources?: IProfileSource[];
+}
+
+interface IProfileSource {
+  uid: string;
+  prefix?: string;
+}
+
+interface IGlobalChainItem {
+  uid: string;
+  enabled: boolean;
 }
 
 interface IProfilesConfig {
   current?: string;
   valid?: string[];
   items?: IProfileItem[];
+  global_chain?: IGlobalChainItem[];
 }
 
 interface IVergeTestItem {
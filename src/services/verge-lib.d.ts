@@ -0,0 +1,60 @@
+// 增强脚本中全局 `verge` 工具库的类型声明，供编辑器补全使用
+
+type VergeProxy = string | { name: string; [key: string]: any };
+
+interface VergeUrl {
+  href: string;
+  protocol: string;
+  username: string;
+  password: string;
+  hostname: string;
+  port: string;
+  pathname: string;
+  search: string;
+  hash: string;
+  params: Record<string, string>;
+}
+
+declare const verge: {
+  readonly version: string;
+  readonly yaml: {
+    parse(text: string): any;
+    stringify(value: any): string;
+  };
+  readonly base64: {
+    encode(text: string): string;
+    decode(text: string): string;
+  };
+  readonly url: {
+    parse(text: string): VergeUrl;
+  };
+  readonly proxies: {
+    names(list: VergeProxy[]): string[];
+    filter<T extends VergeProxy>(
+      list: T[],
+      pattern: string | RegExp,
+      flags?: string,
+    ): T[];
+    exclude<T extends VergeProxy>(
+      list: T[],
+      pattern: string | RegExp,
+      flags?: string,
+    ): T[];
+    rename<T extends VergeProxy>(
+      list: T[],
+      pattern: string | RegExp,
+      replacement: string,
+      flags?: string,
+    ): T[];
+    renameInConfig(
+      config: any,
+      pattern: string | RegExp,
+      replacement: string,
+      flags?: string,
+    ): any;
+  };
+  readonly profiles: {
+    /** 按名称或 uid 读取其他订阅的节点，找不到时为 null */
+    proxies(name: string): readonly any[] | null;
+  };
+};
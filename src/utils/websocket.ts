@@ -1,5 +1,16 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import Sockette, { type SocketteOptions } from "sockette";
 
+/**
+ * 推送地址的前缀：有 TCP 控制器时为 `server`，只开放 unix socket 时为空串（经由后端订阅），
+ * 两者都没有时返回 null
+ */
+export const getStreamServer = (info: IClashInfo): string | null => {
+  if (info.server) return info.server;
+  return info.unix_socket ? "" : null;
+};
+
 /**
  * A wrapper of Sockette that will automatically reconnect up to `maxError` before emitting an error event.
  */
@@ -52,6 +63,11 @@ export const createAuthSockette = (
   opt: SocketteOptions,
   maxError = 10,
 ) => {
+  // 只开放 unix socket 时，路径形如 `/traffic`，由后端订阅后转发
+  if (baseUrl.startsWith("/")) {
+    return createBackendSockette(baseUrl, opt);
+  }
+
   // 确保baseUrl格式正确
   let url = baseUrl;
   if (!url.startsWith("ws://") && !url.startsWith("wss://")) {
@@ -250,6 +266,71 @@ export const createAuthSockette = (
   return socketLike;
 };
 
+/**
+ * 通过后端订阅控制器推送，消息经 `verge://clash-stream` 事件下发，接口与 createAuthSockette 一致
+ */
+function createBackendSockette(path: string, opt: SocketteOptions) {
+  const anyOpt = opt as any;
+  let subscriptionId: number | null = null;
+  let unlisten: (() => void) | null = null;
+  let closed = true;
+
+  const cleanup = () => {
+    closed = true;
+    unlisten?.();
+    unlisten = null;
+    if (subscriptionId !== null) {
+      invoke("clash_api_unsubscribe", { id: subscriptionId }).catch(() => {});
+      subscriptionId = null;
+    }
+  };
+
+  const connect = async () => {
+    closed = false;
+    try {
+      const off = await listen<{ id: number; data: unknown }>(
+        "verge://clash-stream",
+        (event) => {
+          if (event.payload.id !== subscriptionId) return;
+          anyOpt.onmessage?.({ data: JSON.stringify(event.payload.data) });
+        },
+      );
+      const id = await invoke<number>("clash_api_subscribe", { path });
+      // 订阅期间已被关闭
+      if (closed) {
+        off();
+        invoke("clash_api_unsubscribe", { id }).catch(() => {});
+        return;
+      }
+      unlisten = off;
+      subscriptionId = id;
+      console.log(`[WebSocket] 通过后端订阅: ${path}`);
+      anyOpt.onopen?.(new Event("open"));
+    } catch (error) {
+      console.error(`[WebSocket] 后端订阅失败: ${path}`, error);
+      anyOpt.onerror?.(
+        new ErrorEvent("error", { message: `后端订阅失败: ${error}` } as any),
+      );
+    }
+  };
+
+  connect();
+
+  return {
+    close: cleanup,
+    reconnect: () => {
+      cleanup();
+      connect();
+    },
+    json: () => {},
+    send: () => {},
+    open: () => {
+      cleanup();
+      connect();
+    },
+  };
+}
+
 // 创建一个空的WebSocket对象
 function createDummySocket() {
   return {